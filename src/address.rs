@@ -0,0 +1,35 @@
+use std::fmt::Debug;
+
+use clap::Parser;
+use stellar_strkey::StrkeyPublicKeyEd25519;
+
+use crate::utils;
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Secret key (S...) to print the public key (G...) of
+    #[clap(
+        long = "secret-key",
+        env = "SOROBAN_SECRET_KEY",
+        hide_env_values = true
+    )]
+    secret_key: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("cannot parse secret key")]
+    CannotParseSecretKey,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        let key =
+            utils::parse_private_key(&self.secret_key).map_err(|_| Error::CannotParseSecretKey)?;
+        println!(
+            "{}",
+            StrkeyPublicKeyEd25519(key.public.to_bytes()).to_string()
+        );
+        Ok(())
+    }
+}
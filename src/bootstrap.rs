@@ -0,0 +1,155 @@
+use std::fmt::Debug;
+
+use clap::Parser;
+use soroban_env_host::xdr::{
+    ContractDataEntry, Error as XdrError, Hash, LedgerEntry, LedgerEntryData, LedgerEntryExt,
+    LedgerKey, LedgerKeyContractData, ReadXdr, ScSpecTypeDef, ScStatic, ScVal,
+};
+
+use crate::{
+    rpc::{self, Client},
+    snapshot,
+    strval::{self, StrValError},
+    utils,
+};
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// RPC server endpoint to fetch the contract's state from
+    #[clap(long, alias = "from-ledger-snapshot-url")]
+    rpc_server_url: String,
+    /// Contract ID to fetch the code and data of
+    #[clap(long = "id")]
+    contract_id: String,
+    /// Storage key of a data entry to fetch (symbols only)
+    #[clap(long = "key", multiple = true)]
+    keys: Vec<String>,
+    /// Storage key of a data entry to fetch (base64-encoded XDR)
+    #[clap(long = "key-xdr", multiple = true)]
+    keys_xdr: Vec<String>,
+    /// File to persist ledger state
+    #[clap(long, parse(from_os_str), default_value(".soroban/ledger.json"))]
+    ledger_file: std::path::PathBuf,
+    /// Note to record in the ledger file alongside this change (for debugging)
+    #[clap(long)]
+    note: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("parsing contract ID {contract_id}: {error}")]
+    CannotParseContractId {
+        contract_id: String,
+        error: utils::ParseContractIdError,
+    },
+    #[error("parsing key {key}: {error}")]
+    CannotParseKey { key: String, error: StrValError },
+    #[error("parsing XDR key {key}: {error}")]
+    CannotParseXdrKey { key: String, error: XdrError },
+    #[error("reading file {filepath}: {error}")]
+    CannotReadLedgerFile {
+        filepath: std::path::PathBuf,
+        error: snapshot::Error,
+    },
+    #[error("committing file {filepath}: {error}")]
+    CannotCommitLedgerFile {
+        filepath: std::path::PathBuf,
+        error: snapshot::Error,
+    },
+    #[error(transparent)]
+    Rpc(#[from] rpc::Error),
+}
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        let contract_id = utils::contract_id_from_str(&self.contract_id).map_err(|e| {
+            Error::CannotParseContractId {
+                contract_id: self.contract_id.clone(),
+                error: e,
+            }
+        })?;
+
+        let client = Client::new(&self.rpc_server_url);
+        let fetched: Vec<(LedgerKey, Option<LedgerEntry>)> =
+            fetch_contract_entries(&client, contract_id, &self.keys, &self.keys_xdr)
+                .await?
+                .into_iter()
+                .map(|(key, entry)| (key, Some(entry)))
+                .collect();
+
+        let state = snapshot::read(&self.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
+            filepath: self.ledger_file.clone(),
+            error: e,
+        })?;
+        snapshot::commit(
+            state.entries,
+            state.info,
+            state.notes,
+            self.note.as_deref(),
+            fetched.iter().map(|(k, v)| (k, v)),
+            &self.ledger_file,
+        )
+        .map_err(|e| Error::CannotCommitLedgerFile {
+            filepath: self.ledger_file.clone(),
+            error: e,
+        })?;
+        eprintln!(
+            "Fetched {} entries of contract {}",
+            fetched.len(),
+            hex::encode(contract_id)
+        );
+        Ok(())
+    }
+}
+
+/// Fetches the code of the contract and its data entries with the given keys (symbols, or
+/// base64-encoded XDR) from the rpc server. It can't list the entries of a contract, so any other
+/// entry has to be asked for by key.
+pub async fn fetch_contract_entries(
+    client: &Client,
+    contract_id: [u8; 32],
+    keys: &[String],
+    keys_xdr: &[String],
+) -> Result<Vec<(LedgerKey, LedgerEntry)>, Error> {
+    let mut scval_keys = vec![ScVal::Static(ScStatic::LedgerKeyContractCode)];
+    for key in keys {
+        scval_keys.push(
+            strval::from_string(key, &ScSpecTypeDef::Symbol, &[]).map_err(|e| {
+                Error::CannotParseKey {
+                    key: key.clone(),
+                    error: e,
+                }
+            })?,
+        );
+    }
+    for key in keys_xdr {
+        scval_keys.push(
+            ScVal::from_xdr_base64(key).map_err(|e| Error::CannotParseXdrKey {
+                key: key.clone(),
+                error: e,
+            })?,
+        );
+    }
+
+    let mut fetched = Vec::new();
+    for key in scval_keys {
+        let data = client
+            .get_contract_data(&hex::encode(contract_id), key.clone())
+            .await?;
+        let entry = LedgerEntry {
+            last_modified_ledger_seq: data.last_modified_ledger_seq,
+            data: LedgerEntryData::ContractData(ContractDataEntry {
+                contract_id: Hash(contract_id),
+                key: key.clone(),
+                val: data.val,
+            }),
+            ext: LedgerEntryExt::V0,
+        };
+        let ledger_key = LedgerKey::ContractData(LedgerKeyContractData {
+            contract_id: Hash(contract_id),
+            key,
+        });
+        fetched.push((ledger_key, entry));
+    }
+    Ok(fetched)
+}
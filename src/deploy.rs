@@ -1,9 +1,8 @@
 use std::array::TryFromSliceError;
 use std::num::ParseIntError;
-use std::{fmt::Debug, fs, io};
+use std::{fmt::Debug, io};
 
-use clap::Parser;
-use hex::FromHexError;
+use clap::{ArgGroup, Parser};
 use rand::Rng;
 use sha2::{Digest, Sha256};
 use soroban_env_host::xdr::HashIdPreimageSourceAccountContractId;
@@ -13,13 +12,14 @@ use soroban_env_host::xdr::{
     OperationBody, Preconditions, PublicKey, ScObject, ScStatic::LedgerKeyContractCode, ScVal,
     SequenceNumber, Transaction, TransactionEnvelope, TransactionExt, Uint256, VecM, WriteXdr,
 };
-use soroban_env_host::HostError;
+use soroban_env_host::{meta, HostError};
 
 use crate::rpc::{self, Client};
 use crate::snapshot::{self, get_default_ledger_info};
 use crate::utils;
 
 #[derive(Parser, Debug)]
+#[clap(group(ArgGroup::new("signer").args(&["secret-key", "mnemonic"])))]
 pub struct Cmd {
     /// WASM file to deploy
     #[clap(long, parse(from_os_str))]
@@ -42,22 +42,54 @@ pub struct Cmd {
         conflicts_with = "rpc-server-url"
     )]
     ledger_file: std::path::PathBuf,
+    /// Note to record in the ledger file alongside this change (for debugging)
+    #[clap(long, conflicts_with = "rpc-server-url")]
+    note: Option<String>,
+    /// File to write the contract ID to (in the format of the global --id-format), e.g. to
+    /// capture it in scripts
+    #[clap(long, parse(from_os_str))]
+    contract_id_out: Option<std::path::PathBuf>,
+    /// Overwrite the existing code of the contract in the ledger file without prompting
+    #[clap(long, alias = "force", conflicts_with = "rpc-server-url")]
+    clobber: bool,
+    /// Answer yes to any confirmation prompt (e.g. when running non-interactively)
+    #[clap(long)]
+    yes: bool,
 
     /// RPC server endpoint
     #[clap(
         long,
         required_unless_present = "contract-id",
         conflicts_with = "contract-id",
-        requires = "secret-key",
+        requires = "signer",
         requires = "network-passphrase"
     )]
     rpc_server_url: Option<String>,
     /// Secret 'S' key used to sign the transaction sent to the rpc server
     #[clap(long = "secret-key", env = "SOROBAN_SECRET_KEY")]
     secret_key: Option<String>,
+    /// BIP-39 mnemonic to derive the key used to sign the transaction sent to the rpc server from
+    #[clap(long, env = "SOROBAN_MNEMONIC", hide_env_values = true)]
+    mnemonic: Option<String>,
+    /// SEP-0005 derivation path of the key to derive from the mnemonic
+    #[clap(long, default_value = utils::DEFAULT_HD_PATH)]
+    hd_path: String,
     /// Network passphrase to sign the transaction sent to the rpc server
     #[clap(long = "network-passphrase")]
     network_passphrase: Option<String>,
+    /// Fee (in stroops) to pay for the transaction sent to the rpc server
+    #[clap(
+        long,
+        default_value = "100",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    fee: u32,
+    /// How to get the sequence number of the source account (see the possible values)
+    #[clap(long, arg_enum, default_value = "fetch")]
+    sequence_strategy: rpc::SequenceStrategy,
+    /// Current sequence number of the source account, for --sequence-strategy provided
+    #[clap(long, required_if_eq("sequence-strategy", "provided"))]
+    sequence: Option<i64>,
 
     /// Custom salt 32-byte salt for the token id
     #[clap(
@@ -65,11 +97,17 @@ pub struct Cmd {
         conflicts_with_all = &["contract-id", "ledger-file"],
     )]
     salt: Option<String>,
+    /// String to derive the salt from (its SHA-256 hash), for reproducible contract ids
+    #[clap(
+        long,
+        conflicts_with_all = &["salt", "contract-id", "ledger-file"],
+    )]
+    salt_seed: Option<String>,
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
-    #[error(transparent)]
+    #[error("host error: {}", utils::HostErrorDisplay(.0))]
     Host(#[from] HostError),
     #[error("error parsing int: {0}")]
     ParseIntError(#[from] ParseIntError),
@@ -79,6 +117,8 @@ pub enum Error {
     Xdr(#[from] XdrError),
     #[error("jsonrpc error: {0}")]
     JsonRpc(#[from] jsonrpsee_core::Error),
+    #[error("contract was built for env interface version {contract}, but the sandbox host is version {host}")]
+    IncompatibleContractVersion { contract: u64, host: u64 },
     #[error("cannot parse salt: {salt}")]
     CannotParseSalt { salt: String },
     #[error("reading file {filepath}: {error}")]
@@ -91,6 +131,11 @@ pub enum Error {
         filepath: std::path::PathBuf,
         error: io::Error,
     },
+    #[error("writing contract ID to {filepath}: {error}")]
+    CannotWriteContractId {
+        filepath: std::path::PathBuf,
+        error: std::io::Error,
+    },
     #[error("committing file {filepath}: {error}")]
     CannotCommitLedgerFile {
         filepath: std::path::PathBuf,
@@ -99,20 +144,23 @@ pub enum Error {
     #[error("cannot parse contract ID {contract_id}: {error}")]
     CannotParseContractId {
         contract_id: String,
-        error: FromHexError,
+        error: utils::ParseContractIdError,
     },
     #[error("cannot parse secret key")]
     CannotParseSecretKey,
+    #[error("not overwriting the existing code of contract {contract_id} (use --clobber to overwrite it)")]
+    ContractOverwriteDeclined { contract_id: String },
     #[error(transparent)]
     Rpc(#[from] rpc::Error),
 }
 
 impl Cmd {
     pub async fn run(&self) -> Result<(), Error> {
-        let contract = fs::read(&self.wasm).map_err(|e| Error::CannotReadContractFile {
-            filepath: self.wasm.clone(),
-            error: e,
-        })?;
+        let contract =
+            utils::read_wasm_file(&self.wasm).map_err(|e| Error::CannotReadContractFile {
+                filepath: self.wasm.clone(),
+                error: e,
+            })?;
 
         if self.rpc_server_url.is_some() {
             return self.run_against_rpc_server(contract).await;
@@ -133,36 +181,80 @@ impl Cmd {
                 filepath: self.ledger_file.clone(),
                 error: e,
             })?;
-        utils::add_contract_to_ledger_entries(&mut state.1, contract_id, contract)?;
+        utils::check_contract_interface_version(&contract).map_err(|v| {
+            Error::IncompatibleContractVersion {
+                contract: v,
+                host: meta::INTERFACE_VERSION,
+            }
+        })?;
+        if !utils::confirm_overwrite(
+            &state.entries,
+            contract_id,
+            &contract,
+            self.clobber || self.yes,
+        ) {
+            return Err(Error::ContractOverwriteDeclined {
+                contract_id: hex::encode(contract_id),
+            });
+        }
+        utils::add_contract_to_ledger_entries(&mut state.entries, contract_id, contract)?;
 
-        snapshot::commit(state.1, get_default_ledger_info(), [], &self.ledger_file).map_err(
-            |e| Error::CannotCommitLedgerFile {
-                filepath: self.ledger_file.clone(),
-                error: e,
-            },
-        )?;
+        snapshot::commit(
+            state.entries,
+            get_default_ledger_info(),
+            state.notes,
+            self.note.as_deref(),
+            [],
+            &self.ledger_file,
+        )
+        .map_err(|e| Error::CannotCommitLedgerFile {
+            filepath: self.ledger_file.clone(),
+            error: e,
+        })?;
+        self.write_contract_id(contract_id)
+    }
+
+    fn write_contract_id(&self, contract_id: [u8; 32]) -> Result<(), Error> {
+        if let Some(f) = &self.contract_id_out {
+            utils::write_contract_id(f, &utils::contract_id_to_string(&contract_id)).map_err(
+                |e| Error::CannotWriteContractId {
+                    filepath: f.clone(),
+                    error: e,
+                },
+            )?;
+        }
         Ok(())
     }
 
     async fn run_against_rpc_server(&self, contract: Vec<u8>) -> Result<(), Error> {
-        let salt: [u8; 32] = match &self.salt {
+        let salt: [u8; 32] = match (&self.salt, &self.salt_seed) {
             // Hack: re-use contract_id_from_str to parse the 32-byte salt hex.
-            Some(h) => utils::contract_id_from_str(h)
+            (Some(h), _) => utils::contract_id_from_str(h)
                 .map_err(|_| Error::CannotParseSalt { salt: h.clone() })?,
-            None => rand::thread_rng().gen::<[u8; 32]>(),
+            (None, Some(seed)) => {
+                let salt: [u8; 32] = Sha256::digest(seed).into();
+                eprintln!("Salt: {}", hex::encode(salt));
+                salt
+            }
+            (None, None) => rand::thread_rng().gen::<[u8; 32]>(),
         };
 
         let client = Client::new(self.rpc_server_url.as_ref().unwrap());
-        let key = utils::parse_private_key(self.secret_key.as_ref().unwrap())
-            .map_err(|_| Error::CannotParseSecretKey)?;
+        let key = utils::signing_key(
+            self.secret_key.as_deref(),
+            self.mnemonic.as_deref(),
+            &self.hd_path,
+        )
+        .map_err(|_| Error::CannotParseSecretKey)?;
 
         // Get the account sequence number
         let public_strkey =
             stellar_strkey::StrkeyPublicKeyEd25519(key.public.to_bytes()).to_string();
-        let account_details = client.get_account(&public_strkey).await?;
-        // TODO: create a cmdline parameter for the fee instead of simply using the minimum fee
-        let fee: u32 = 100;
-        let sequence = account_details.sequence.parse::<i64>()?;
+        eprintln!("Source account: {}", public_strkey);
+        let sequence = client
+            .account_sequence(&public_strkey, self.sequence_strategy, self.sequence)
+            .await?;
+        let fee = self.fee;
         let (tx, contract_id) = build_create_contract_tx(
             contract,
             sequence + 1,
@@ -172,9 +264,14 @@ impl Cmd {
             &key,
         )?;
 
-        println!("Contract ID: {}", hex::encode(contract_id.0));
+        println!(
+            "Contract ID: {}",
+            utils::contract_id_to_string(&contract_id.0)
+        );
+        self.write_contract_id(contract_id.0)?;
 
-        client.send_transaction(&tx).await?;
+        client.send_transaction(&tx).await?.report();
+        rpc::record_sequence(self.sequence_strategy, &public_strkey, sequence + 1)?;
 
         Ok(())
     }
@@ -0,0 +1,74 @@
+use std::fmt::Debug;
+
+use clap::Parser;
+use serde_json::json;
+use soroban_env_host::xdr::{self, LedgerEntryData, LedgerKey, ScVal};
+
+use crate::{
+    snapshot,
+    strval::{self, StrValError},
+    utils,
+};
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Contract ID to dump the data of
+    #[clap(long = "id")]
+    contract_id: String,
+    /// File to persist ledger state
+    #[clap(long, parse(from_os_str), default_value(".soroban/ledger.json"))]
+    ledger_file: std::path::PathBuf,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("parsing contract ID {contract_id}: {error}")]
+    CannotParseContractId {
+        contract_id: String,
+        error: utils::ParseContractIdError,
+    },
+    #[error("reading file {filepath}: {error}")]
+    CannotReadLedgerFile {
+        filepath: std::path::PathBuf,
+        error: snapshot::Error,
+    },
+    #[error("cannot print value {value:?}: {error}")]
+    CannotPrintValue { value: ScVal, error: StrValError },
+    #[error("cannot print as json: {0}")]
+    CannotPrintJson(#[from] serde_json::Error),
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        let contract_id = utils::contract_id_from_str(&self.contract_id).map_err(|e| {
+            Error::CannotParseContractId {
+                contract_id: self.contract_id.clone(),
+                error: e,
+            }
+        })?;
+        let state = snapshot::read(&self.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
+            filepath: self.ledger_file.clone(),
+            error: e,
+        })?;
+
+        let to_json = |value: &ScVal| {
+            strval::to_json(value).map_err(|e| Error::CannotPrintValue {
+                value: value.clone(),
+                error: e,
+            })
+        };
+        let mut entries = Vec::new();
+        for (k, v) in &state.entries {
+            if let (LedgerKey::ContractData(kd), LedgerEntryData::ContractData(vd)) = (k, &v.data) {
+                if kd.contract_id.0 == contract_id
+                    && kd.key != ScVal::Static(xdr::ScStatic::LedgerKeyContractCode)
+                {
+                    entries.push(json!({ "key": to_json(&vd.key)?, "value": to_json(&vd.val)? }));
+                }
+            }
+        }
+
+        println!("{}", utils::to_json_string(&entries)?);
+        Ok(())
+    }
+}
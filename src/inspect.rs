@@ -1,8 +1,8 @@
+use crate::utils;
 use clap::Parser;
 use soroban_env_host::xdr::{Error as XdrError, ReadXdr, ScEnvMetaEntry, ScSpecEntry};
 use std::{
     fmt::Debug,
-    fs,
     io::{self, Cursor},
 };
 
@@ -33,10 +33,11 @@ impl Cmd {
     pub fn run(&self) -> Result<(), Error> {
         println!("File: {}", self.wasm.to_string_lossy());
 
-        let contents = fs::read(&self.wasm).map_err(|e| Error::CannotReadContractFile {
-            filepath: self.wasm.clone(),
-            error: e,
-        })?;
+        let contents =
+            utils::read_wasm_file(&self.wasm).map_err(|e| Error::CannotReadContractFile {
+                filepath: self.wasm.clone(),
+                error: e,
+            })?;
 
         let mut env_meta: Option<&[u8]> = None;
         let mut spec: Option<&[u8]> = None;
@@ -1,34 +1,53 @@
 use std::num::ParseIntError;
-use std::{fmt::Debug, fs, io, rc::Rc};
+use std::{
+    fmt::Debug,
+    fs,
+    io::{self, BufRead, Read, Write},
+    sync::mpsc::RecvTimeoutError,
+    time::{Duration, Instant},
+};
 
-use clap::Parser;
-use hex::FromHexError;
+use clap::{ArgEnum, ArgGroup, CommandFactory, Parser, ValueSource};
+use hex::{FromHex, FromHexError};
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::Value;
 use soroban_env_host::xdr::{
-    InvokeHostFunctionOp, LedgerFootprint, Memo, MuxedAccount, Operation, OperationBody,
-    Preconditions, ScStatic, ScVec, SequenceNumber, Transaction, TransactionEnvelope,
-    TransactionExt, VecM,
+    ContractEvent, ContractEventBody, ContractEventV0, InvokeHostFunctionOp, LedgerFootprint, Memo,
+    MuxedAccount, Operation, OperationBody, Preconditions, ScMap, ScStatic, ScSymbol, ScVec,
+    SequenceNumber, Transaction, TransactionEnvelope, TransactionExt, TransactionV1Envelope, VecM,
 };
 use soroban_env_host::{
     budget::{Budget, CostType},
     events::HostEvent,
-    storage::Storage,
+    meta,
+    storage::{AccessType, Storage},
     xdr::{
-        AccountId, Error as XdrError, HostFunction, PublicKey, ReadXdr, ScHostStorageErrorCode,
-        ScObject, ScSpecEntry, ScStatus, ScVal, Uint256,
+        AccountId, Error as XdrError, HostFunction, LedgerEntry, LedgerKey, PublicKey, ReadXdr,
+        ScObject, ScSpecEntry, ScSpecFunctionInputV0, ScSpecFunctionV0, ScVal, Uint256, WriteXdr,
     },
-    Host, HostError,
+    Host, HostError, LedgerInfo,
 };
 use soroban_spec::read::FromWasmError;
 use stellar_strkey::StrkeyPublicKeyEd25519;
 
 use crate::rpc::Client;
 use crate::{
-    rpc, snapshot,
+    bootstrap, rpc, snapshot,
     strval::{self, StrValError},
     utils,
 };
 
-#[derive(Parser, Debug)]
+// Number of times the contract code is fetched with --retry-on-reset
+const CONTRACT_CODE_FETCH_ATTEMPTS: u32 = 5;
+
+// Arguments whose values are redacted by --print-command
+const SECRET_ARGS: [&str; 2] = ["secret-key", "mnemonic"];
+
+#[derive(Parser, Debug, Clone)]
+#[clap(group(ArgGroup::new("signer").args(&["secret-key", "mnemonic", "source-account"])))]
 pub struct Cmd {
     /// Contract ID to invoke
     #[clap(long = "id")]
@@ -47,18 +66,109 @@ pub struct Cmd {
     /// WASM file to deploy to the contract ID and invoke
     #[clap(long, parse(from_os_str))]
     wasm: Option<std::path::PathBuf>,
+    /// SHA-256 hash (hex) of a wasm already deployed in the ledger file, to deploy to the
+    /// contract ID and invoke
+    #[clap(long, conflicts_with_all = &["wasm", "rpc-server-url"])]
+    wasm_hash: Option<String>,
+    /// JSON file with the contract spec entries, used instead of the spec embedded in the wasm
+    #[clap(long, parse(from_os_str))]
+    spec_file: Option<std::path::PathBuf>,
     /// Function name to execute
-    #[clap(long = "fn")]
-    function: String,
-    /// Argument to pass to the function
+    #[clap(
+        long = "fn",
+        conflicts_with = "function-positional",
+        required_unless_present_any = &["function-positional", "list"]
+    )]
+    function: Option<String>,
+    /// Function name to execute (alternative to --fn, e.g. `soroban invoke --id <ID> <FN>`)
+    #[clap(value_name = "FN")]
+    function_positional: Option<String>,
+    /// List the functions of the contract and their parameters instead of invoking one
+    #[clap(long, conflicts_with_all = &["function", "function-positional"])]
+    list: bool,
+    /// Argument to pass to the function, or `name=value` to pass it by parameter name (`-` reads
+    /// it from stdin)
     #[clap(long = "arg", value_name = "arg", multiple = true)]
     args: Vec<String>,
-    /// Argument to pass to the function (base64-encoded xdr)
+    /// Argument to pass to the function (base64-encoded xdr, `name=value` and `-` work like with
+    /// --arg)
     #[clap(long = "arg-xdr", value_name = "arg-xdr", multiple = true)]
     args_xdr: Vec<String>,
+    /// Read the arguments to pass to the function from stdin, one per line (the overwrite
+    /// prompt can't be answered then, use --clobber or --yes)
+    #[clap(long, conflicts_with_all = &["args", "args-xdr"])]
+    args_stdin: bool,
+    /// Arguments to pass to the function as a JSON object keyed by parameter name, e.g.
+    /// '{"to":"G...","amount":"100"}'
+    #[clap(long, conflicts_with_all = &["args", "args-xdr", "args-stdin"])]
+    args_json: Option<String>,
+    /// Don't print the result or the events (errors and --cost are still printed)
+    #[clap(long)]
+    quiet: bool,
     /// Output the cost execution to stderr
     #[clap(long = "cost")]
     cost: bool,
+    /// Sort the cost output by cost type, most expensive first
+    #[clap(long)]
+    cost_sort: bool,
+    /// Output the decoded ledger keys of the footprint to stderr
+    #[clap(long)]
+    footprint: bool,
+    /// Only print the footprint of the invocation (base64 xdr of a LedgerFootprint), recorded in
+    /// the sandbox or simulated on the rpc server, without committing or submitting anything
+    #[clap(long)]
+    print_footprint_only: bool,
+    /// Output the cost execution to stderr without committing any change to the ledger file
+    #[clap(long, conflicts_with = "rpc-server-url")]
+    dry_run_cost: bool,
+    /// Fail (with exit code 4, without committing any change to the ledger file) if the
+    /// invocation uses more than this many cpu instructions
+    #[clap(long, value_name = "n", conflicts_with = "rpc-server-url")]
+    assert_cpu_under: Option<u64>,
+    /// Fail (with exit code 4, without committing any change to the ledger file) if the
+    /// invocation uses more than this many bytes of memory
+    #[clap(long, value_name = "n", conflicts_with = "rpc-server-url")]
+    assert_mem_under: Option<u64>,
+    /// Fail (with exit code 4, without committing any change to the ledger file) if the result
+    /// isn't this value, given like an argument (map entries can be in any order)
+    #[clap(long, value_name = "value", conflicts_with = "rpc-server-url")]
+    assert_result: Option<String>,
+    /// After the invocation, invoke this argument-less function of the same contract in the same
+    /// session and print its result too (e.g. to check a set/get round-trip)
+    #[clap(long, value_name = "fn", conflicts_with = "rpc-server-url")]
+    then_read: Option<String>,
+    /// Type of output to generate (json prints a single JSON document on stdout, with the result
+    /// along with the events and cost)
+    #[clap(long, arg_enum, default_value("string"))]
+    output: Output,
+    /// Only print the first n bytes, elements or entries of a result that has more of them
+    /// (string output only)
+    #[clap(long, value_name = "n")]
+    truncate_output: Option<usize>,
+    /// Print the result as this type instead of its own, e.g. `string` for bytes holding UTF-8
+    /// text: string, hex, account_id, vec<type> or option<type> (string output only)
+    #[clap(long, value_name = "type")]
+    decode_result_as: Option<strval::DecodeAs>,
+    /// Only output contract events whose first topic is the given symbol
+    #[clap(long, value_name = "symbol")]
+    event_topic: Vec<String>,
+    /// Fail (without committing any change to the ledger file) unless a contract event whose
+    /// first topic is the given symbol was emitted
+    #[clap(long, value_name = "symbol", conflicts_with = "rpc-server-url")]
+    require_event: Vec<String>,
+    /// Stop (without committing any change to the ledger file) when a contract event whose first
+    /// topic is the given symbol was emitted, printing it and the cost, and exit with code 5.
+    /// The check happens after the invocation, which can't be interrupted midway
+    #[clap(long, value_name = "symbol", conflicts_with = "rpc-server-url")]
+    halt_on_event: Vec<String>,
+    /// Print contract events whose first topic names a struct in the contract spec as JSON
+    /// objects, with the event data labeled by the struct's field names
+    #[clap(long)]
+    output_events_schema: bool,
+    /// Write all the emitted events to this file as JSON, one object per line tagged with its
+    /// type (contract or debug)
+    #[clap(long, parse(from_os_str), conflicts_with = "rpc-server-url")]
+    events_file: Option<std::path::PathBuf>,
     /// File to persist ledger state
     #[clap(
         long,
@@ -67,12 +177,63 @@ pub struct Cmd {
         conflicts_with = "rpc-server-url"
     )]
     ledger_file: std::path::PathBuf,
+    /// File to write the resulting ledger state to, leaving --ledger-file untouched (defaults to
+    /// --ledger-file itself)
+    #[clap(long, parse(from_os_str), conflicts_with = "rpc-server-url")]
+    out_ledger_file: Option<std::path::PathBuf>,
+    /// Note to record in the ledger file alongside this change (for debugging)
+    #[clap(long, conflicts_with = "rpc-server-url")]
+    note: Option<String>,
+    /// Print the fully-resolved command line to stderr before running it (e.g. for bug reports)
+    #[clap(long)]
+    print_command: bool,
+    /// Print a summary of what the command will do to stderr before doing it
+    #[clap(long)]
+    explain: bool,
+    /// Print the summary of --explain and stop without invoking the function
+    #[clap(long)]
+    dry_run: bool,
+    /// Deprecated, use --timeout (in seconds) instead
+    #[clap(long, hide = true, conflicts_with = "rpc-server-url")]
+    time_budget_ms: Option<u64>,
+    /// Time limit (in seconds) for the invocation in the sandbox, or for each rpc request and for
+    /// the transaction to be applied when using an rpc server
+    #[clap(long, value_name = "seconds", conflicts_with = "time-budget-ms")]
+    timeout: Option<u64>,
+    /// Base reserve (in stroops) seen by the contract in the sandbox, instead of the ledger file's
+    #[clap(long, conflicts_with = "rpc-server-url")]
+    base_reserve: Option<u32>,
+    /// Fetch the code of the contract (and the data entries given with --fork-key) from this rpc
+    /// server and invoke it in the sandbox on top of the ledger file, without sending a
+    /// transaction or writing the ledger file
+    #[clap(
+        long,
+        value_name = "url",
+        conflicts_with_all = &["rpc-server-url", "out-ledger-file"]
+    )]
+    fork_rpc_url: Option<String>,
+    /// Storage key of a data entry to fetch for --fork-rpc-url (symbols only)
+    #[clap(long, requires = "fork-rpc-url")]
+    fork_key: Vec<String>,
+    /// Storage key of a data entry to fetch for --fork-rpc-url (base64-encoded XDR)
+    #[clap(long, requires = "fork-rpc-url")]
+    fork_key_xdr: Vec<String>,
+    /// Network passphrase (which the network id is the hash of) seen by the contract in the
+    /// sandbox, instead of the ledger file's
+    #[clap(long, conflicts_with = "rpc-server-url")]
+    sandbox_network_passphrase: Option<String>,
+    /// Overwrite the existing code of the contract in the ledger file without prompting
+    #[clap(long, alias = "force", conflicts_with = "rpc-server-url")]
+    clobber: bool,
+    /// Answer yes to any confirmation prompt (e.g. when running non-interactively)
+    #[clap(long)]
+    yes: bool,
 
     /// RPC server endpoint
     #[clap(
         long,
         conflicts_with = "account-id",
-        requires = "secret-key",
+        requires = "signer",
         requires = "network-passphrase"
     )]
     rpc_server_url: Option<String>,
@@ -83,9 +244,98 @@ pub struct Cmd {
         requires = "rpc-server-url"
     )]
     secret_key: Option<String>,
+    /// BIP-39 mnemonic to derive the key used to sign the transaction sent to the rpc server from
+    #[clap(
+        long,
+        env = "SOROBAN_MNEMONIC",
+        hide_env_values = true,
+        requires = "rpc-server-url"
+    )]
+    mnemonic: Option<String>,
+    /// Account to build the transaction for when it's signed elsewhere (see --unsigned-out)
+    #[clap(long, requires = "unsigned-out")]
+    source_account: Option<StrkeyPublicKeyEd25519>,
+    /// Write the unsigned transaction (base64 xdr) to this file instead of signing and sending
+    /// it, e.g. to sign it with a hardware wallet and send it with `soroban submit`
+    #[clap(long, parse(from_os_str), requires = "rpc-server-url")]
+    unsigned_out: Option<std::path::PathBuf>,
+    /// SEP-0005 derivation path of the key to derive from the mnemonic
+    #[clap(long, default_value = utils::DEFAULT_HD_PATH)]
+    hd_path: String,
     /// Network passphrase to sign the transaction sent to the rpc server
     #[clap(long = "network-passphrase", requires = "rpc-server-url")]
     network_passphrase: Option<String>,
+    /// Fee (in stroops) to pay for the transaction sent to the rpc server
+    #[clap(
+        long,
+        default_value = "100",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    fee: u32,
+    /// Estimate the fee from the simulated resource usage, on top of the base fee, at the rates
+    /// given with --cpu-insns-per-stroop and --mem-bytes-per-stroop
+    #[clap(
+        long,
+        requires = "rpc-server-url",
+        requires = "cpu-insns-per-stroop",
+        requires = "mem-bytes-per-stroop"
+    )]
+    auto_fee: bool,
+    /// Only simulate the invocation on the rpc server and print its footprint, result, cost and
+    /// fee, without submitting anything
+    #[clap(long, requires = "rpc-server-url")]
+    simulate: bool,
+    /// Maximum fee (in stroops) to pay, the transaction isn't submitted if the fee exceeds it
+    #[clap(long, requires = "rpc-server-url")]
+    max_fee: Option<u32>,
+    /// CPU instructions to estimate the resource fee for, instead of the simulated ones. Only the
+    /// fee changes: no resource limits are set on the transaction.
+    #[clap(
+        long,
+        value_name = "n",
+        requires = "rpc-server-url",
+        requires = "cpu-insns-per-stroop",
+        requires = "mem-bytes-per-stroop"
+    )]
+    fee_estimate_cpu_insns: Option<u64>,
+    /// Memory bytes to estimate the resource fee for, instead of the simulated ones. Only the fee
+    /// changes: no resource limits are set on the transaction.
+    #[clap(
+        long,
+        value_name = "n",
+        requires = "rpc-server-url",
+        requires = "cpu-insns-per-stroop",
+        requires = "mem-bytes-per-stroop"
+    )]
+    fee_estimate_mem_bytes: Option<u64>,
+    /// Number of CPU instructions the network charges one stroop of resource fee for
+    #[clap(
+        long,
+        value_name = "n",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    cpu_insns_per_stroop: Option<u64>,
+    /// Number of memory bytes the network charges one stroop of resource fee for
+    #[clap(
+        long,
+        value_name = "n",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    mem_bytes_per_stroop: Option<u64>,
+    /// Retry fetching the contract code a few times (with backoff) if the rpc server doesn't
+    /// find it yet, e.g. right after deploying it
+    #[clap(long, requires = "rpc-server-url")]
+    retry_on_reset: bool,
+    /// How to get the sequence number of the source account (see the possible values)
+    #[clap(long, arg_enum, default_value = "fetch")]
+    sequence_strategy: rpc::SequenceStrategy,
+    /// Current sequence number of the source account, for --sequence-strategy provided
+    #[clap(
+        long,
+        requires = "rpc-server-url",
+        required_if_eq("sequence-strategy", "provided")
+    )]
+    sequence: Option<i64>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -96,15 +346,19 @@ pub enum Error {
     CannotParseXdrArg { arg: String, error: XdrError },
     #[error("cannot add contract to ledger entries: {0}")]
     CannotAddContractToLedgerEntries(XdrError),
-    #[error(transparent)]
-    // TODO: the Display impl of host errors is pretty user-unfriendly
-    //       (it just calls Debug). I think we can do better than that
+    #[error("host error: {}", utils::HostErrorDisplay(.0))]
     Host(#[from] HostError),
+    #[error("cannot finish the host session, it is still referenced elsewhere")]
+    CannotFinishHost,
     #[error("reading file {filepath}: {error}")]
     CannotReadLedgerFile {
         filepath: std::path::PathBuf,
         error: snapshot::Error,
     },
+    #[error("cannot parse wasm hash {hash}: {error}")]
+    CannotParseWasmHash { hash: String, error: FromHexError },
+    #[error("no contract code with wasm hash {0} found in the ledger file")]
+    WasmHashNotFound(String),
     #[error("reading file {filepath}: {error}")]
     CannotReadContractFile {
         filepath: std::path::PathBuf,
@@ -118,65 +372,170 @@ pub enum Error {
     #[error("cannot parse contract ID {contract_id}: {error}")]
     CannotParseContractId {
         contract_id: String,
-        error: FromHexError,
+        error: utils::ParseContractIdError,
+    },
+    #[error(
+        "function {function} was not found in contract {contract_id} (available: {})",
+        .available.join(", ")
+    )]
+    FunctionNotFoundInContractSpec {
+        function: String,
+        contract_id: String,
+        available: Vec<String>,
     },
-    #[error("function {0} was not found in the contract")]
-    FunctionNotFoundInContractSpec(String),
     #[error("parsing contract spec: {0}")]
     CannotParseContractSpec(FromWasmError),
-    #[error("unexpected number of arguments: {provided} (function {function} expects {expected} argument(s))")]
+    #[error("reading spec file {filepath}: {error}")]
+    CannotReadSpecFile {
+        filepath: std::path::PathBuf,
+        error: io::Error,
+    },
+    #[error("parsing spec file {filepath}: {error}")]
+    CannotParseSpecFile {
+        filepath: std::path::PathBuf,
+        error: serde_json::Error,
+    },
+    #[error("unexpected number of arguments: {provided} (function {function} of contract {contract_id} expects {expected} argument(s))")]
     UnexpectedArgumentCount {
         provided: usize,
         expected: usize,
         function: String,
+        contract_id: String,
+    },
+    #[error("function name {function} is {length} characters long, the maximum is {max}")]
+    FunctionNameTooLong {
+        function: String,
+        length: usize,
+        max: usize,
     },
-    #[error("function name {0} is too long")]
-    FunctionNameTooLong(String),
     #[error("argument count ({current}) surpasses maximum allowed count ({maximum})")]
     MaxNumberOfArgumentsReached { current: usize, maximum: usize },
     #[error("cannot print result {result:?}: {error}")]
     CannotPrintResult { result: ScVal, error: StrValError },
+    #[error("cannot print result {result:?}: {error}")]
+    CannotPrintJsonResult {
+        result: ScVal,
+        error: serde_json::Error,
+    },
+    #[error("printing the json output: {0}")]
+    CannotPrintJsonOutput(io::Error),
     #[error("xdr processing error: {0}")]
     Xdr(#[from] XdrError),
     #[error("error parsing int: {0}")]
     ParseIntError(#[from] ParseIntError),
     #[error("cannot parse secret key")]
     CannotParseSecretKey,
+    #[error("not overwriting the existing code of contract {contract_id} (use --clobber to overwrite it)")]
+    ContractOverwriteDeclined { contract_id: String },
     #[error(transparent)]
     Rpc(#[from] rpc::Error),
     #[error("unexpected contract code data type: {0:?}")]
     UnexpectedContractCodeDataType(ScVal),
+    #[error("invocation did not finish within the time budget ({0} ms), the ledger file was left untouched")]
+    Timeout(u64),
+    #[error("invocation thread panicked")]
+    InvocationThreadPanicked,
+    #[error("contract was built for env interface version {contract}, but the sandbox host is version {host}")]
+    IncompatibleContractVersion { contract: u64, host: u64 },
+    #[error("parsing --args-json: {0}")]
+    CannotParseArgsJson(serde_json::Error),
+    #[error("function {function} has no parameter {name} (expected: {})", .valid.join(", "))]
+    UnknownArgument {
+        name: String,
+        function: String,
+        valid: Vec<String>,
+    },
+    #[error("arguments of function {0} must either all be named (name=value) or all positional")]
+    MixedNamedAndPositionalArgs(String),
+    #[error("argument {0} is given more than once")]
+    DuplicateArgument(String),
+    #[error("missing argument {name} of function {function}")]
+    MissingArgument { name: String, function: String },
+    #[error("reading arguments from stdin: {0}")]
+    CannotReadArgsFromStdin(io::Error),
+    #[error("writing unsigned transaction to {filepath}: {error}")]
+    CannotWriteUnsignedTransaction {
+        filepath: std::path::PathBuf,
+        error: utils::WriteTransactionError,
+    },
+    #[error("writing events to {filepath}: {error}")]
+    CannotWriteEventsFile {
+        filepath: std::path::PathBuf,
+        error: io::Error,
+    },
+    #[error("fetching the contract state to fork: {0}")]
+    CannotFetchForkedState(bootstrap::Error),
+    #[error("used {actual} {resource}, more than the allowed {allowed}")]
+    CostAssertionFailed {
+        resource: &'static str,
+        actual: u64,
+        allowed: u64,
+    },
+    #[error("result {actual} doesn't match the expected {expected}")]
+    ResultAssertionFailed { expected: String, actual: String },
+    #[error("function {0} doesn't return a result to assert")]
+    FunctionHasNoResult(String),
+    #[error("no contract event with first topic {0} was emitted")]
+    RequiredEventNotEmitted(String),
+    #[error("halted on event #{index} with first topic {topic}")]
+    HaltedOnEvent { index: usize, topic: String },
+    #[error("only one argument can be read from stdin (with --arg - or --arg-xdr -)")]
+    MultipleArgsFromStdin,
+    #[error("transaction fee ({fee} stroops) exceeds the maximum fee ({max_fee} stroops)")]
+    FeeExceedsMaxFee { fee: u32, max_fee: u32 },
+}
+
+// Parts of the --output json document, gathered while running and printed together by `run`
+type JsonOutput = serde_json::Map<String, Value>;
+
+// Prints the --output json document as a single JSON value, if anything was gathered
+fn write_json_output(out: &mut impl Write, json: &JsonOutput) -> io::Result<()> {
+    if json.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "{}", utils::to_json_string(json)?)
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ArgEnum)]
+pub enum Output {
+    /// String
+    String,
+    /// Json
+    Json,
+}
+
+impl Error {
+    /// Exit code for the process to fail with, so scripts can tell failed assertions apart
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::CostAssertionFailed { .. } | Error::ResultAssertionFailed { .. } => 4,
+            Error::HaltedOnEvent { .. } => 5,
+            _ => 1,
+        }
+    }
 }
 
+/// Argument to pass to the function, in the form it was given on the command line
 #[derive(Clone, Debug)]
-enum Arg {
+pub enum Arg {
     Arg(String),
     ArgXdr(String),
 }
 
 impl Cmd {
-    fn build_host_function_parameters(
-        &self,
-        contract_id: [u8; 32],
-        wasm: &[u8],
-        matches: &clap::ArgMatches,
-    ) -> Result<ScVec, Error> {
-        // Get the function spec from the contract code
-        let spec_entries =
-            soroban_spec::read::from_wasm(wasm).map_err(Error::CannotParseContractSpec)?;
-        let spec = spec_entries
-            .iter()
-            .find_map(|e| {
-                if let ScSpecEntry::FunctionV0(f) = e {
-                    if f.name.to_string_lossy() == self.function {
-                        return Some(f);
-                    }
-                }
-                None
-            })
-            .ok_or_else(|| Error::FunctionNotFoundInContractSpec(self.function.clone()))?;
+    fn function_name(&self) -> &str {
+        self.function
+            .as_deref()
+            .or(self.function_positional.as_deref())
+            .unwrap_or_default()
+    }
 
-        // Re-assemble the function args, to match the order given on the command line
+    // Re-assemble the function args, to match the order given on the command line
+    fn ordered_args(&self, matches: &clap::ArgMatches) -> Vec<Arg> {
+        if self.args_stdin {
+            // Populated from stdin by `run`, there can't be any --arg or --arg-xdr
+            return self.args.iter().map(|a| Arg::Arg(a.clone())).collect();
+        }
         let indexed_args: Vec<(usize, Arg)> = matches
             .indices_of("args")
             .unwrap_or_default()
@@ -191,55 +550,311 @@ impl Cmd {
             .collect();
         let mut all_indexed_args: Vec<(usize, Arg)> = [indexed_args, indexed_args_xdr].concat();
         all_indexed_args.sort_by(|a, b| a.0.cmp(&b.0));
+        all_indexed_args.into_iter().map(|(_, arg)| arg).collect()
+    }
 
-        // Parse the function arguments
-        let inputs = &spec.inputs;
-        if all_indexed_args.len() != inputs.len() {
-            return Err(Error::UnexpectedArgumentCount {
-                provided: all_indexed_args.len(),
-                expected: inputs.len(),
-                function: self.function.clone(),
+    fn explanation(&self, args: &[Arg]) -> String {
+        let invocation = format!(
+            "Will invoke function {} of contract {} with {} argument(s)",
+            self.function_name(),
+            self.contract_id,
+            args.len()
+        );
+        if let Some(rpc_server_url) = &self.rpc_server_url {
+            let source = if let Some(account) = &self.source_account {
+                account.to_string()
+            } else {
+                utils::signing_key(
+                    self.secret_key.as_deref(),
+                    self.mnemonic.as_deref(),
+                    &self.hd_path,
+                )
+                .ok()
+                .map_or_else(
+                    || "the signing account".to_string(),
+                    |key| StrkeyPublicKeyEd25519(key.public.to_bytes()).to_string(),
+                )
+            };
+            format!(
+                "{} as {}, on network '{}' via {}, in 1 transaction",
+                invocation,
+                source,
+                self.network_passphrase.as_deref().unwrap_or_default(),
+                rpc_server_url
+            )
+        } else {
+            format!(
+                "{} as {}, in the ledger file {}",
+                invocation,
+                self.account_id,
+                self.ledger_file.display()
+            )
+        }
+    }
+
+    // Renders the invocation as a command line from the parsed arguments, with aliases expanded
+    // and environment variables resolved (secrets are redacted, defaults left out)
+    fn command_line(&self, matches: &clap::ArgMatches, args: &[Arg]) -> String {
+        let mut command_line = "soroban invoke".to_string();
+        for arg in Cmd::command().get_arguments() {
+            let id = arg.get_id();
+            // The function args are appended in the order they were given, below
+            if id == "args" || id == "args-xdr" {
+                continue;
+            }
+            match matches.value_source(id) {
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable) => (),
+                _ => continue,
+            }
+            let flag = arg.get_long().map(|long| format!("--{}", long));
+            let values: Vec<String> = matches
+                .get_raw(id)
+                .map(|values| {
+                    values
+                        .map(|value| value.to_string_lossy().to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            if values.is_empty() {
+                if let Some(flag) = &flag {
+                    command_line.push(' ');
+                    command_line.push_str(flag);
+                }
+            }
+            for value in values {
+                if let Some(flag) = &flag {
+                    command_line.push(' ');
+                    command_line.push_str(flag);
+                }
+                let value = if SECRET_ARGS.contains(&id) {
+                    "<redacted>"
+                } else {
+                    value.as_str()
+                };
+                command_line.push(' ');
+                command_line.push_str(&utils::shell_quote(value));
+            }
+        }
+        // With --args-stdin the args were read from stdin, which the command line reads again
+        if !self.args_stdin {
+            for arg in args {
+                let (flag, value) = match arg {
+                    Arg::Arg(s) => ("--arg", s),
+                    Arg::ArgXdr(s) => ("--arg-xdr", s),
+                };
+                command_line.push(' ');
+                command_line.push_str(flag);
+                command_line.push(' ');
+                command_line.push_str(&utils::shell_quote(value));
+            }
+        }
+        command_line
+    }
+
+    fn print_result(&self, res: ScVal, json: &mut JsonOutput) -> Result<(), Error> {
+        if self.quiet {
+            return Ok(());
+        }
+        if self.output == Output::Json {
+            let res_json =
+                serde_json::to_value(&res).map_err(|e| Error::CannotPrintJsonResult {
+                    result: res.clone(),
+                    error: e,
+                })?;
+            // Only the sandbox returns a second result, the one of --then-read
+            let key = if json.contains_key("result") {
+                "thenReadResult"
+            } else {
+                "result"
+            };
+            json.insert(key.to_string(), res_json);
+            return Ok(());
+        }
+        let (res, rest) = match self.truncate_output {
+            Some(max) => truncate_result(res, max)?,
+            None => (res, None),
+        };
+        let res_str = match &self.decode_result_as {
+            Some(t) => strval::to_string_as(&res, t),
+            None => strval::to_string(&res),
+        }
+        .map_err(|e| Error::CannotPrintResult {
+            result: res,
+            error: e,
+        })?;
+        match rest {
+            Some(rest) => println!("{} \u{2026} ({})", res_str, rest),
+            None => println!("{}", res_str),
+        }
+        Ok(())
+    }
+
+    fn assert_result(
+        &self,
+        contract_id: [u8; 32],
+        wasm: &[u8],
+        expected: &str,
+        actual: &ScVal,
+    ) -> Result<(), Error> {
+        let spec_entries = self.spec_entries(wasm)?;
+        let spec = find_function_spec(&spec_entries, self.function_name(), contract_id)?;
+        let output = spec
+            .outputs
+            .first()
+            .ok_or_else(|| Error::FunctionHasNoResult(self.function_name().to_string()))?;
+        let expected_val = strval::from_string(expected, output, &spec_entries).map_err(|e| {
+            Error::CannotParseArg {
+                arg: expected.to_string(),
+                error: e,
+            }
+        })?;
+        let expected_val =
+            strval::canonicalize(&expected_val).map_err(|e| Error::CannotParseArg {
+                arg: expected.to_string(),
+                error: e,
+            })?;
+        let actual_val = strval::canonicalize(actual).map_err(|e| Error::CannotPrintResult {
+            result: actual.clone(),
+            error: e,
+        })?;
+        if expected_val != actual_val {
+            return Err(Error::ResultAssertionFailed {
+                expected: expected.to_string(),
+                actual: strval::to_string(actual).unwrap_or_else(|_| format!("{:?}", actual)),
             });
         }
+        Ok(())
+    }
 
-        let parsed_args = all_indexed_args
-            .iter()
-            .zip(inputs.iter())
-            .map(|(arg, input)| match &arg.1 {
-                Arg::ArgXdr(s) => ScVal::from_xdr_base64(s).map_err(|e| Error::CannotParseXdrArg {
-                    arg: s.clone(),
+    // Prints the cost to stderr, or adds it to the --output json document
+    fn print_rpc_cost(&self, cost: &rpc::Cost, json: &mut JsonOutput) {
+        if self.output == Output::Json {
+            json.insert("cost".to_string(), serde_json::to_value(cost).unwrap());
+        } else {
+            eprintln!("Cpu Insns: {}", cost.cpu_insns);
+            eprintln!("Mem Bytes: {}", cost.mem_bytes);
+        }
+    }
+
+    // Prints the events to stderr, or adds them to the --output json document as one array
+    fn print_events(
+        &self,
+        events: &[HostEvent],
+        wasm: &[u8],
+        json: &mut JsonOutput,
+    ) -> Result<(), Error> {
+        let event_spec = if self.output_events_schema {
+            self.spec_entries(wasm)?
+        } else {
+            Vec::new()
+        };
+        let mut events_json = Vec::new();
+        for (i, event) in events.iter().enumerate() {
+            if let HostEvent::Contract(e) = event {
+                if !self.event_topic.is_empty() && !has_first_topic(e, &self.event_topic) {
+                    continue;
+                }
+            }
+            if self.output == Output::Json {
+                // The index is the position among all emitted events, like the #N: of the
+                // string output, so it stays stable when events are filtered by --event-topic
+                events_json.push(match event {
+                    HostEvent::Contract(e) => serde_json::json!({
+                        "index": i,
+                        "type": "contract",
+                        "event": typed_event(e, &event_spec)
+                            .unwrap_or_else(|| serde_json::to_value(e).unwrap()),
+                    }),
+                    HostEvent::Debug(e) => serde_json::json!({
+                        "index": i,
+                        "type": "debug",
+                        "event": e.to_string(),
+                    }),
+                });
+                continue;
+            }
+            eprint!("#{}: ", i);
+            match event {
+                HostEvent::Contract(e) => match typed_event(e, &event_spec) {
+                    Some(typed) => eprintln!("event: {}", utils::to_json_string(&typed).unwrap()),
+                    None => eprintln!("event: {}", utils::to_json_string(&e).unwrap()),
+                },
+                // With --then-read the events of both invocations are mixed, so there's no
+                // telling which function emitted it
+                HostEvent::Debug(e) => eprintln!(
+                    "{}",
+                    utils::describe_debug_event(
+                        e,
+                        self.then_read.is_none().then(|| self.function_name())
+                    )
+                ),
+            }
+        }
+        if self.output == Output::Json {
+            json.insert("events".to_string(), Value::Array(events_json));
+        }
+        Ok(())
+    }
+
+    fn spec_entries(&self, wasm: &[u8]) -> Result<Vec<ScSpecEntry>, Error> {
+        if let Some(f) = &self.spec_file {
+            let contents = fs::read(f).map_err(|e| Error::CannotReadSpecFile {
+                filepath: f.clone(),
+                error: e,
+            })?;
+            serde_json::from_slice::<Vec<ScSpecEntry>>(&contents).map_err(|e| {
+                Error::CannotParseSpecFile {
+                    filepath: f.clone(),
                     error: e,
-                }),
-                Arg::Arg(s) => {
-                    strval::from_string(s, &input.type_).map_err(|e| Error::CannotParseArg {
-                        arg: s.clone(),
-                        error: e,
-                    })
                 }
             })
-            .collect::<Result<Vec<_>, _>>()?;
-
-        // Add the contract ID and the function name to the arguments
-        let mut complete_args = vec![
-            ScVal::Object(Some(ScObject::Bytes(contract_id.try_into().unwrap()))),
-            ScVal::Symbol(
-                (&self.function)
-                    .try_into()
-                    .map_err(|_| Error::FunctionNameTooLong(self.function.clone()))?,
-            ),
-        ];
-        complete_args.extend_from_slice(parsed_args.as_slice());
-        let complete_args_len = complete_args.len();
+        } else {
+            soroban_spec::read::from_wasm(wasm).map_err(Error::CannotParseContractSpec)
+        }
+    }
 
-        complete_args
-            .try_into()
-            .map_err(|_| Error::MaxNumberOfArgumentsReached {
-                current: complete_args_len,
-                maximum: ScVec::default().max_len(),
-            })
+    fn build_host_function_parameters(
+        &self,
+        contract_id: [u8; 32],
+        wasm: &[u8],
+        args: &[Arg],
+    ) -> Result<ScVec, Error> {
+        // Get the function spec from the spec file, or else from the contract code
+        let spec_entries = self.spec_entries(wasm)?;
+        let args = resolve_args(
+            args,
+            self.args_json.as_deref(),
+            !self.args_stdin,
+            &spec_entries,
+            self.function_name(),
+            contract_id,
+        )?;
+        host_function_parameters(contract_id, self.function_name(), &args, &spec_entries)
     }
 
     pub async fn run(&self, matches: &clap::ArgMatches) -> Result<(), Error> {
+        let mut json = JsonOutput::new();
+        let res = if self.args_stdin {
+            let mut cmd = self.clone();
+            cmd.args = io::stdin()
+                .lock()
+                .lines()
+                .collect::<Result<_, _>>()
+                .map_err(Error::CannotReadArgsFromStdin)?;
+            cmd.run_with_args(matches, &mut json).await
+        } else {
+            self.run_with_args(matches, &mut json).await
+        };
+        // Whatever was gathered is printed even if a later check failed, like the string output
+        write_json_output(&mut io::stdout(), &json).map_err(Error::CannotPrintJsonOutput)?;
+        res
+    }
+
+    async fn run_with_args(
+        &self,
+        matches: &clap::ArgMatches,
+        json: &mut JsonOutput,
+    ) -> Result<(), Error> {
         let contract_id: [u8; 32] =
             utils::contract_id_from_str(&self.contract_id).map_err(|e| {
                 Error::CannotParseContractId {
@@ -248,28 +863,140 @@ impl Cmd {
                 }
             })?;
 
+        if self.list {
+            return self.list_functions(contract_id).await;
+        }
+
+        // Fail fast, before reading the spec or reaching the rpc server
+        function_symbol(self.function_name())?;
+        if let Some(read_fn) = &self.then_read {
+            function_symbol(read_fn)?;
+        }
+
+        // The order of the arguments is only known from clap, everything else works off this list
+        let args = self.ordered_args(matches);
+        if self.print_command {
+            eprintln!("{}", self.command_line(matches, &args));
+        }
+        if self.explain || self.dry_run {
+            eprintln!("{}", self.explanation(&args));
+            if self.dry_run {
+                return Ok(());
+            }
+        }
+
         if self.rpc_server_url.is_some() {
-            return self.run_against_rpc_server(contract_id, matches).await;
+            return self.run_against_rpc_server(contract_id, &args, json).await;
         }
 
-        self.run_in_sandbox(contract_id, matches)
+        let forked_entries = if let Some(fork_rpc_url) = &self.fork_rpc_url {
+            let client =
+                Client::new(fork_rpc_url).with_timeout(self.timeout.map(Duration::from_secs));
+            bootstrap::fetch_contract_entries(
+                &client,
+                contract_id,
+                &self.fork_key,
+                &self.fork_key_xdr,
+            )
+            .await
+            .map_err(Error::CannotFetchForkedState)?
+        } else {
+            Vec::new()
+        };
+
+        if self.time_budget_ms.is_some() {
+            eprintln!("warning: --time-budget-ms is deprecated, use --timeout (in seconds)");
+        }
+        if let Some(time_budget_ms) = self.time_budget_ms() {
+            return self.run_in_sandbox_with_time_budget(
+                contract_id,
+                args,
+                forked_entries,
+                time_budget_ms,
+                json,
+            );
+        }
+
+        self.run_in_sandbox(contract_id, &args, forked_entries, None, json)
+    }
+
+    fn time_budget_ms(&self) -> Option<u64> {
+        self.timeout.map(|t| t * 1000).or(self.time_budget_ms)
+    }
+
+    fn run_in_sandbox_with_time_budget(
+        &self,
+        contract_id: [u8; 32],
+        args: Vec<Arg>,
+        forked_entries: Vec<(LedgerKey, LedgerEntry)>,
+        time_budget_ms: u64,
+        json: &mut JsonOutput,
+    ) -> Result<(), Error> {
+        let cmd = self.clone();
+        let deadline = utils::Deadline::default();
+        let res = utils::run_with_timeout(
+            Duration::from_millis(time_budget_ms),
+            &deadline,
+            move |deadline| {
+                let mut json = JsonOutput::new();
+                let res = cmd.run_in_sandbox(
+                    contract_id,
+                    &args,
+                    forked_entries,
+                    Some(deadline),
+                    &mut json,
+                );
+                (res, json)
+            },
+        );
+        match res {
+            Ok((res, worker_json)) => {
+                json.extend(worker_json);
+                res
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                // The budget is only known once the host returns, which it may not have yet
+                match deadline.budget() {
+                    Some((cpu_insns, mem_bytes)) => {
+                        eprintln!("Cpu Insns: {}", cpu_insns);
+                        eprintln!("Mem Bytes: {}", mem_bytes);
+                    }
+                    None => eprintln!("Budget consumed: unknown, the contract was still running"),
+                }
+                Err(Error::Timeout(time_budget_ms))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(Error::InvocationThreadPanicked),
+        }
     }
 
     async fn run_against_rpc_server(
         &self,
         contract_id: [u8; 32],
-        matches: &clap::ArgMatches,
+        args: &[Arg],
+        json: &mut JsonOutput,
     ) -> Result<(), Error> {
-        let client = Client::new(self.rpc_server_url.as_ref().unwrap());
-        let key = utils::parse_private_key(self.secret_key.as_ref().unwrap())
+        let client = Client::new(self.rpc_server_url.as_ref().unwrap())
+            .with_timeout(self.timeout.map(Duration::from_secs));
+        // The key is only missing when the transaction is signed elsewhere
+        let (source_account, key) = if let Some(account) = &self.source_account {
+            (account.0, None)
+        } else {
+            let key = utils::signing_key(
+                self.secret_key.as_deref(),
+                self.mnemonic.as_deref(),
+                &self.hd_path,
+            )
             .map_err(|_| Error::CannotParseSecretKey)?;
+            (key.public.to_bytes(), Some(key))
+        };
 
         // Get the account sequence number
-        let public_strkey = StrkeyPublicKeyEd25519(key.public.to_bytes()).to_string();
-        let account_details = client.get_account(&public_strkey).await?;
-        // TODO: create a cmdline parameter for the fee instead of simply using the minimum fee
-        let fee: u32 = 100;
-        let sequence = account_details.sequence.parse::<i64>()?;
+        let public_strkey = StrkeyPublicKeyEd25519(source_account).to_string();
+        eprintln!("Source account: {}", public_strkey);
+        let sequence = client
+            .account_sequence(&public_strkey, self.sequence_strategy, self.sequence)
+            .await?;
+        let fee = self.fee;
 
         // Get the contract
         let wasm = if let Some(f) = &self.wasm {
@@ -277,38 +1004,84 @@ impl Cmd {
             // TODO: as a workaround (RPC server doesn't yet implement getContractData)
             //       we allow supplying the contract in the commandline
             //       we should consider removing this later on
-            fs::read(f).map_err(|e| Error::CannotReadContractFile {
+            utils::read_wasm_file(f).map_err(|e| Error::CannotReadContractFile {
                 filepath: f.clone(),
                 error: e,
             })?
         } else {
             // Get the contract from the network
-            let contract_data = client
-                .get_contract_data(
-                    &hex::encode(contract_id),
-                    ScVal::Static(ScStatic::LedgerKeyContractCode),
-                )
-                .await?;
+            let contract_data = self.fetch_contract_code(&client, contract_id).await?;
 
-            match ScVal::from_xdr_base64(contract_data.xdr)? {
+            match contract_data.val {
                 ScVal::Object(Some(ScObject::Bytes(bytes))) => bytes.to_vec(),
                 scval => return Err(Error::UnexpectedContractCodeDataType(scval)),
             }
         };
 
         // Get the ledger footprint
-        let host_function_params =
-            self.build_host_function_parameters(contract_id, &wasm, matches)?;
+        let host_function_params = self.build_host_function_parameters(contract_id, &wasm, args)?;
         let tx_without_footprint = build_invoke_contract_tx(
             host_function_params.clone(),
             None,
             sequence + 1,
             fee,
-            self.network_passphrase.as_ref().unwrap(),
-            &key,
+            source_account,
         )?;
+        let tx_without_footprint = match &key {
+            Some(key) => utils::sign_transaction(
+                key,
+                &tx_without_footprint,
+                self.network_passphrase.as_ref().unwrap(),
+            )?,
+            None => TransactionEnvelope::Tx(TransactionV1Envelope {
+                tx: tx_without_footprint,
+                signatures: VecM::default(),
+            }),
+        };
         let simulation_response = client.simulate_transaction(&tx_without_footprint).await?;
         let footprint = LedgerFootprint::from_xdr_base64(simulation_response.footprint)?;
+        if self.footprint || self.simulate {
+            print_footprint(
+                footprint.read_only.as_slice(),
+                footprint.read_write.as_slice(),
+            );
+        }
+        if self.print_footprint_only {
+            println!("{}", footprint.to_xdr_base64()?);
+            return Ok(());
+        }
+        let fee = if self.auto_fee
+            || self.fee_estimate_cpu_insns.is_some()
+            || self.fee_estimate_mem_bytes.is_some()
+        {
+            let simulated = simulation_response.cost.clone();
+            let cost = rpc::Cost {
+                cpu_insns: self
+                    .fee_estimate_cpu_insns
+                    .map_or(simulated.cpu_insns, |i| i.to_string()),
+                mem_bytes: self
+                    .fee_estimate_mem_bytes
+                    .map_or(simulated.mem_bytes, |m| m.to_string()),
+            };
+            // Both rates are required by clap along with the options that get here
+            estimate_fee(
+                fee,
+                &cost,
+                self.cpu_insns_per_stroop.unwrap(),
+                self.mem_bytes_per_stroop.unwrap(),
+            )?
+        } else {
+            fee
+        };
+
+        if self.simulate {
+            for result in &simulation_response.results {
+                self.print_result(ScVal::from_xdr_base64(result.xdr.clone())?, json)?;
+            }
+            self.print_rpc_cost(&simulation_response.cost, json);
+            eprintln!("Fee: {}", fee);
+            return Ok(());
+        }
 
         // Send the final transaction with the actual footprint
         let tx = build_invoke_contract_tx(
@@ -316,99 +1089,984 @@ impl Cmd {
             Some(footprint),
             sequence + 1,
             fee,
-            self.network_passphrase.as_ref().unwrap(),
+            source_account,
+        )?;
+
+        if let Some(max_fee) = self.max_fee {
+            if fee > max_fee {
+                return Err(Error::FeeExceedsMaxFee { fee, max_fee });
+            }
+        }
+        if let Some(path) = &self.unsigned_out {
+            utils::write_unsigned_transactions(path, &[tx]).map_err(|e| {
+                Error::CannotWriteUnsignedTransaction {
+                    filepath: path.clone(),
+                    error: e,
+                }
+            })?;
+            eprintln!("Unsigned transaction written to {}", path.display());
+            return Ok(());
+        }
+        let key = key.expect("--source-account requires --unsigned-out");
+        let (tx, tx_hash) = utils::sign_transaction_with_hash(
             &key,
+            &tx,
+            self.network_passphrase.as_ref().unwrap(),
         )?;
+        let submitted = client.send_transaction(&tx).await?;
+        submitted.report();
+        if submitted.hash != hex::encode(tx_hash) {
+            eprintln!(
+                "warning: the rpc server reported hash {} for transaction {}",
+                submitted.hash,
+                hex::encode(tx_hash)
+            );
+        }
+        rpc::record_sequence(self.sequence_strategy, &public_strkey, sequence + 1)?;
 
-        client.send_transaction(&tx).await?;
-        // TODO: print results
-        // TODO: print cost
+        for result in &submitted.results {
+            self.print_result(ScVal::from_xdr_base64(result.xdr.clone())?, json)?;
+        }
+        if self.cost {
+            if let Some(cost) = &submitted.cost {
+                self.print_rpc_cost(cost, json);
+            }
+        }
 
         Ok(())
     }
 
+    // Prints the functions in the spec of the contract, from wherever an invocation would get it
+    async fn list_functions(&self, contract_id: [u8; 32]) -> Result<(), Error> {
+        let wasm = if self.spec_file.is_some() {
+            Vec::new()
+        } else if let Some(f) = &self.wasm {
+            utils::read_wasm_file(f).map_err(|e| Error::CannotReadContractFile {
+                filepath: f.clone(),
+                error: e,
+            })?
+        } else if let Some(rpc_server_url) = &self.rpc_server_url {
+            let client =
+                Client::new(rpc_server_url).with_timeout(self.timeout.map(Duration::from_secs));
+            match self.fetch_contract_code(&client, contract_id).await?.val {
+                ScVal::Object(Some(ScObject::Bytes(bytes))) => bytes.to_vec(),
+                scval => return Err(Error::UnexpectedContractCodeDataType(scval)),
+            }
+        } else {
+            let state =
+                snapshot::read(&self.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
+                    filepath: self.ledger_file.clone(),
+                    error: e,
+                })?;
+            let mut storage = Storage::with_recording_footprint(snapshot::snap_from(&state));
+            utils::get_contract_wasm_from_storage(&mut storage, contract_id)?
+        };
+        for entry in self.spec_entries(&wasm)? {
+            if let ScSpecEntry::FunctionV0(f) = entry {
+                let inputs: Vec<String> = f
+                    .inputs
+                    .iter()
+                    .map(|i| format!("{}: {:?}", i.name.to_string_lossy(), i.type_))
+                    .collect();
+                println!("{}({})", f.name.to_string_lossy(), inputs.join(", "));
+            }
+        }
+        Ok(())
+    }
+
+    async fn fetch_contract_code(
+        &self,
+        client: &Client,
+        contract_id: [u8; 32],
+    ) -> Result<rpc::ContractData, Error> {
+        let attempts = if self.retry_on_reset {
+            CONTRACT_CODE_FETCH_ATTEMPTS
+        } else {
+            1
+        };
+        let mut backoff = Duration::from_millis(500);
+        let mut attempt = 1;
+        // Providers can take a moment to index newly deployed contracts, so with --retry-on-reset
+        // a not-found is retried a bounded number of times before being reported.
+        loop {
+            let res = client
+                .get_contract_data(
+                    &hex::encode(contract_id),
+                    ScVal::Static(ScStatic::LedgerKeyContractCode),
+                )
+                .await;
+            match res {
+                Err(e) if e.is_not_found() && attempt < attempts => {
+                    eprintln!("Contract code not found yet, retrying in {:?}", backoff);
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                res => return Ok(res?),
+            }
+        }
+    }
+
+    fn sandbox_options(
+        &self,
+        forked_entries: Vec<(LedgerKey, LedgerEntry)>,
+    ) -> Result<SandboxOptions, Error> {
+        let wasm = match &self.wasm {
+            Some(f) => {
+                Some(
+                    utils::read_wasm_file(f).map_err(|e| Error::CannotReadContractFile {
+                        filepath: f.clone(),
+                        error: e,
+                    })?,
+                )
+            }
+            None => None,
+        };
+        let wasm_hash = match &self.wasm_hash {
+            Some(h) => Some(parse_wasm_hash(h)?),
+            None => None,
+        };
+        let spec_entries = match &self.spec_file {
+            Some(_) => Some(self.spec_entries(&[])?),
+            None => None,
+        };
+        // Arguments read from stdin leave no way to answer the prompt
+        let overwrite = if self.clobber || self.yes {
+            Overwrite::Always
+        } else if self.args_stdin {
+            Overwrite::Never
+        } else {
+            Overwrite::Prompt
+        };
+        Ok(SandboxOptions {
+            ledger_file: self.ledger_file.clone(),
+            out_ledger_file: self.out_ledger_file.clone(),
+            note: self.note.clone(),
+            account_id: self.account_id.0,
+            wasm,
+            wasm_hash,
+            overwrite,
+            spec_entries,
+            args_json: self.args_json.clone(),
+            read_stdin_arg: !self.args_stdin,
+            base_reserve: self.base_reserve,
+            network_passphrase: self.sandbox_network_passphrase.clone(),
+            then_read: self.then_read.clone(),
+            forked_entries,
+        })
+    }
+
+    // Changes are only made to the in-memory state until the very end, so the ledger file is
+    // left untouched unless the whole invocation succeeds.
     fn run_in_sandbox(
         &self,
         contract_id: [u8; 32],
-        matches: &clap::ArgMatches,
+        args: &[Arg],
+        forked_entries: Vec<(LedgerKey, LedgerEntry)>,
+        deadline: Option<&utils::Deadline>,
+        json: &mut JsonOutput,
     ) -> Result<(), Error> {
-        // Initialize storage and host
-        // TODO: allow option to separate input and output file
-        let mut state =
-            snapshot::read(&self.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
-                filepath: self.ledger_file.clone(),
-                error: e,
-            })?;
+        for res in self.invoke_sandboxed(contract_id, args, forked_entries, deadline, json)? {
+            self.print_result(res, json)?;
+        }
+        Ok(())
+    }
 
-        // If a file is specified, deploy the contract to storage
-        if let Some(f) = &self.wasm {
-            let contract = fs::read(f).map_err(|e| Error::CannotReadContractFile {
-                filepath: f.clone(),
-                error: e,
+    // Returns the result of the invocation, followed by the one of --then-read (if any)
+    fn invoke_sandboxed(
+        &self,
+        contract_id: [u8; 32],
+        args: &[Arg],
+        forked_entries: Vec<(LedgerKey, LedgerEntry)>,
+        deadline: Option<&utils::Deadline>,
+        json: &mut JsonOutput,
+    ) -> Result<Vec<ScVal>, Error> {
+        let opts = self.sandbox_options(forked_entries)?;
+        let invocation = invoke_in_sandbox(contract_id, self.function_name(), args, &opts)?;
+        if let Some(deadline) = deadline {
+            deadline.record_budget(
+                invocation.budget.get_cpu_insns_count(),
+                invocation.budget.get_mem_bytes_count(),
+            );
+        }
+
+        if !self.halt_on_event.is_empty() {
+            let halted = invocation
+                .events
+                .iter()
+                .enumerate()
+                .find_map(|(i, event)| match event {
+                    HostEvent::Contract(e) if has_first_topic(e, &self.halt_on_event) => {
+                        Some((i, e))
+                    }
+                    _ => None,
+                });
+            if let Some((index, e)) = halted {
+                eprintln!("#{}: event: {}", index, utils::to_json_string(&e).unwrap());
+                print_cost(&invocation.budget, self.cost_sort);
+                return Err(Error::HaltedOnEvent {
+                    index,
+                    topic: first_topic(e).unwrap_or_default(),
+                });
+            }
+        }
+
+        if self.cost || self.dry_run_cost || self.fork_rpc_url.is_some() {
+            if self.output == Output::Json {
+                self.print_rpc_cost(
+                    &rpc::Cost {
+                        cpu_insns: invocation.budget.get_cpu_insns_count().to_string(),
+                        mem_bytes: invocation.budget.get_mem_bytes_count().to_string(),
+                    },
+                    json,
+                );
+            } else {
+                print_cost(&invocation.budget, self.cost_sort);
+            }
+        }
+        for (resource, actual, allowed) in [
+            (
+                "cpu instructions",
+                invocation.budget.get_cpu_insns_count(),
+                self.assert_cpu_under,
+            ),
+            (
+                "memory bytes",
+                invocation.budget.get_mem_bytes_count(),
+                self.assert_mem_under,
+            ),
+        ] {
+            if let Some(allowed) = allowed {
+                if actual > allowed {
+                    return Err(Error::CostAssertionFailed {
+                        resource,
+                        actual,
+                        allowed,
+                    });
+                }
+            }
+        }
+
+        if self.footprint || self.print_footprint_only {
+            let (read_only, read_write): (Vec<_>, Vec<_>) = invocation
+                .storage
+                .footprint
+                .0
+                .iter()
+                .partition(|(_, access)| **access == AccessType::ReadOnly);
+            let keys = |entries: Vec<(&LedgerKey, &AccessType)>| -> Vec<LedgerKey> {
+                entries.into_iter().map(|(k, _)| k.clone()).collect()
+            };
+            let (read_only, read_write) = (keys(read_only), keys(read_write));
+            if self.footprint {
+                print_footprint(&read_only, &read_write);
+            }
+            if self.print_footprint_only {
+                let footprint = LedgerFootprint {
+                    read_only: read_only.try_into()?,
+                    read_write: read_write.try_into()?,
+                };
+                println!("{}", footprint.to_xdr_base64()?);
+                return Ok(Vec::new());
+            }
+        }
+
+        if !self.quiet {
+            self.print_events(&invocation.events, &invocation.wasm, json)?;
+        }
+        if let Some(path) = &self.events_file {
+            write_events_file(path, &invocation.events).map_err(|e| {
+                Error::CannotWriteEventsFile {
+                    filepath: path.clone(),
+                    error: e,
+                }
             })?;
-            utils::add_contract_to_ledger_entries(&mut state.1, contract_id, contract)
-                .map_err(Error::CannotAddContractToLedgerEntries)?;
+        }
+        for topic in &self.require_event {
+            let emitted = invocation.events.iter().any(|event| match event {
+                HostEvent::Contract(e) => has_first_topic(e, std::slice::from_ref(topic)),
+                HostEvent::Debug(_) => false,
+            });
+            if !emitted {
+                return Err(Error::RequiredEventNotEmitted(topic.clone()));
+            }
+        }
+        if let Some(expected) = &self.assert_result {
+            self.assert_result(
+                contract_id,
+                &invocation.wasm,
+                expected,
+                &invocation.results[0],
+            )?;
         }
 
-        let snap = Rc::new(snapshot::Snap {
-            ledger_entries: state.1.clone(),
-        });
-        let mut storage = Storage::with_recording_footprint(snap);
-        let wasm = utils::get_contract_wasm_from_storage(&mut storage, contract_id)?;
-        let h = Host::with_storage_and_budget(storage, Budget::default());
+        if self.dry_run_cost || self.fork_rpc_url.is_some() {
+            return Ok(invocation.results);
+        }
 
-        h.set_source_account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
-            self.account_id.0,
-        ))));
+        // The time budget may have run out while the results were checked and printed
+        if let Some(deadline) = deadline {
+            if !deadline.begin_commit() {
+                return Err(Error::Timeout(self.time_budget_ms().unwrap_or_default()));
+            }
+        }
+        invocation.commit(&opts)?;
+        Ok(invocation.results)
+    }
+}
 
-        let mut ledger_info = state.0.clone();
-        ledger_info.sequence_number += 1;
-        ledger_info.timestamp += 5;
-        h.set_ledger_info(ledger_info.clone());
+/// What to do when the code deployed for an invocation in the sandbox would replace different
+/// existing code of the contract
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Fail with `Error::ContractOverwriteDeclined`
+    #[default]
+    Never,
+    /// Replace the existing code
+    Always,
+    /// Ask on the terminal whether to replace it
+    Prompt,
+}
 
-        let host_function_params =
-            self.build_host_function_parameters(contract_id, &wasm, matches)?;
+/// Options of an invocation in the sandbox, see `invoke_in_sandbox`
+#[derive(Clone, Debug, Default)]
+pub struct SandboxOptions {
+    /// File to read the ledger state from, and to commit the changes to
+    pub ledger_file: std::path::PathBuf,
+    /// File to commit the changes to instead of `ledger_file`
+    pub out_ledger_file: Option<std::path::PathBuf>,
+    /// Note to record in the ledger file alongside the changes
+    pub note: Option<String>,
+    /// Public key (ed25519) of the account to invoke as
+    pub account_id: [u8; 32],
+    /// Code to deploy to the contract ID before invoking it
+    pub wasm: Option<Vec<u8>>,
+    /// SHA-256 hash of code already in the ledger file to deploy to the contract ID before
+    /// invoking it
+    pub wasm_hash: Option<[u8; 32]>,
+    /// What to do when the deployed code would replace different existing code of the contract
+    pub overwrite: Overwrite,
+    /// Spec entries to parse the arguments with, instead of the ones embedded in the contract
+    pub spec_entries: Option<Vec<ScSpecEntry>>,
+    /// Arguments as a JSON object keyed by parameter name, used instead of the given ones
+    pub args_json: Option<String>,
+    /// Replace the argument given as `-` (if any) with the contents of stdin
+    pub read_stdin_arg: bool,
+    /// Base reserve seen by the contract, instead of the ledger file's
+    pub base_reserve: Option<u32>,
+    /// Network passphrase seen by the contract, instead of the ledger file's
+    pub network_passphrase: Option<String>,
+    /// Argument-less function of the same contract to invoke afterwards, in the same session
+    pub then_read: Option<String>,
+    /// Entries layered over the ones of the ledger file
+    pub forked_entries: Vec<(LedgerKey, LedgerEntry)>,
+}
 
-        let res = h.invoke_function(HostFunction::InvokeContract, host_function_params)?;
-        let res_str = strval::to_string(&res).map_err(|e| Error::CannotPrintResult {
-            result: res,
+/// Invocation run in the sandbox by `invoke_in_sandbox`. Its changes are only written to the
+/// ledger file by `commit`.
+pub struct SandboxInvocation {
+    /// Result of the function, followed by the one of `then_read` (if any)
+    pub results: Vec<ScVal>,
+    /// Code of the invoked contract
+    pub wasm: Vec<u8>,
+    pub storage: Storage,
+    pub budget: Budget,
+    pub events: Vec<HostEvent>,
+    state: snapshot::LedgerState,
+    ledger_info: LedgerInfo,
+}
+
+impl SandboxInvocation {
+    /// Writes the changes of the invocation to the ledger file of the options
+    pub fn commit(&self, opts: &SandboxOptions) -> Result<(), Error> {
+        let out_ledger_file = opts.out_ledger_file.as_ref().unwrap_or(&opts.ledger_file);
+        let start = Instant::now();
+        snapshot::commit(
+            self.state.entries.clone(),
+            self.ledger_info.clone(),
+            self.state.notes.clone(),
+            opts.note.as_deref(),
+            &self.storage.map,
+            out_ledger_file,
+        )
+        .map_err(|e| Error::CannotCommitLedgerFile {
+            filepath: out_ledger_file.clone(),
             error: e,
         })?;
+        utils::record_phase("ledger file commit", start);
+        Ok(())
+    }
+}
 
-        println!("{}", res_str);
+/// Invokes a function of a contract on the state of the ledger file, with the arguments in the
+/// order of its parameters (or as `name=value`). The ledger file is left untouched until the
+/// returned invocation is committed.
+pub fn invoke_in_sandbox(
+    contract_id: [u8; 32],
+    function: &str,
+    args: &[Arg],
+    opts: &SandboxOptions,
+) -> Result<SandboxInvocation, Error> {
+    // Initialize storage and host
+    let start = Instant::now();
+    let mut state = snapshot::read(&opts.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
+        filepath: opts.ledger_file.clone(),
+        error: e,
+    })?;
+    utils::record_phase("ledger file read", start);
+    for (key, entry) in &opts.forked_entries {
+        state.entries.insert(key.clone(), entry.clone());
+    }
 
-        let (storage, budget, events) = h.try_finish().map_err(|_h| {
-            HostError::from(ScStatus::HostStorageError(
-                ScHostStorageErrorCode::UnknownError,
-            ))
+    // If code (or the hash of already deployed code) is given, deploy the contract to storage
+    let contract = if let Some(hash) = opts.wasm_hash {
+        Some(
+            utils::find_contract_wasm_by_hash(&state.entries, hash)
+                .ok_or_else(|| Error::WasmHashNotFound(hex::encode(hash)))?,
+        )
+    } else {
+        opts.wasm.clone()
+    };
+    if let Some(contract) = contract {
+        utils::check_contract_interface_version(&contract).map_err(|v| {
+            Error::IncompatibleContractVersion {
+                contract: v,
+                host: meta::INTERFACE_VERSION,
+            }
         })?;
-
-        if self.cost {
-            eprintln!("Cpu Insns: {}", budget.get_cpu_insns_count());
-            eprintln!("Mem Bytes: {}", budget.get_mem_bytes_count());
-            for cost_type in CostType::variants() {
-                eprintln!("Cost ({:?}): {}", cost_type, budget.get_input(*cost_type));
+        let allowed = match opts.overwrite {
+            Overwrite::Never => {
+                !utils::would_overwrite_contract(&state.entries, contract_id, &contract)
             }
+            Overwrite::Always | Overwrite::Prompt => utils::confirm_overwrite(
+                &state.entries,
+                contract_id,
+                &contract,
+                opts.overwrite == Overwrite::Always,
+            ),
+        };
+        if !allowed {
+            return Err(Error::ContractOverwriteDeclined {
+                contract_id: hex::encode(contract_id),
+            });
         }
+        utils::add_contract_to_ledger_entries(&mut state.entries, contract_id, contract)
+            .map_err(Error::CannotAddContractToLedgerEntries)?;
+    }
 
-        for (i, event) in events.0.iter().enumerate() {
-            eprint!("#{}: ", i);
-            match event {
-                HostEvent::Contract(e) => {
-                    eprintln!("event: {}", serde_json::to_string(&e).unwrap());
+    let start = Instant::now();
+    let snap = snapshot::snap_from(&state);
+    let mut storage = Storage::with_recording_footprint(snap);
+    let wasm = utils::get_contract_wasm_from_storage(&mut storage, contract_id)?;
+    let h = Host::with_storage_and_budget(storage, Budget::default());
+
+    h.set_source_account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
+        opts.account_id,
+    ))));
+
+    let mut ledger_info = state.info.clone();
+    ledger_info.sequence_number += 1;
+    ledger_info.timestamp += 5;
+    if let Some(base_reserve) = opts.base_reserve {
+        ledger_info.base_reserve = base_reserve;
+    }
+    if let Some(passphrase) = &opts.network_passphrase {
+        ledger_info.network_passphrase = passphrase.as_bytes().to_vec();
+    }
+    h.set_ledger_info(ledger_info.clone());
+    utils::record_phase("host setup", start);
+
+    let spec_entries = match &opts.spec_entries {
+        Some(spec_entries) => spec_entries.clone(),
+        None => soroban_spec::read::from_wasm(&wasm).map_err(Error::CannotParseContractSpec)?,
+    };
+    let args = resolve_args(
+        args,
+        opts.args_json.as_deref(),
+        opts.read_stdin_arg,
+        &spec_entries,
+        function,
+        contract_id,
+    )?;
+    let host_function_params =
+        host_function_parameters(contract_id, function, &args, &spec_entries)?;
+
+    let start = Instant::now();
+    let res = h.invoke_function(HostFunction::InvokeContract, host_function_params)?;
+    let mut results = vec![res];
+
+    if let Some(read_fn) = &opts.then_read {
+        let params: ScVec = vec![
+            ScVal::Object(Some(ScObject::Bytes(contract_id.try_into().unwrap()))),
+            ScVal::Symbol(function_symbol(read_fn)?),
+        ]
+        .try_into()?;
+        results.push(h.invoke_function(HostFunction::InvokeContract, params)?);
+    }
+
+    let (storage, budget, events) = h.try_finish().map_err(|_h| Error::CannotFinishHost)?;
+    utils::record_phase("invocation", start);
+    Ok(SandboxInvocation {
+        results,
+        wasm,
+        storage,
+        budget,
+        events: events.0,
+        state,
+        ledger_info,
+    })
+}
+
+// Orders the arguments by the parameters of the function (taking them from the JSON object
+// instead if given), and reads the one given as `-` (if any) from stdin
+fn resolve_args(
+    args: &[Arg],
+    args_json: Option<&str>,
+    read_stdin_arg: bool,
+    spec_entries: &[ScSpecEntry],
+    function: &str,
+    contract_id: [u8; 32],
+) -> Result<Vec<Arg>, Error> {
+    let spec = find_function_spec(spec_entries, function, contract_id)?;
+    let args = if let Some(args_json) = args_json {
+        args_from_json(args_json, &spec.inputs, function)?
+    } else {
+        order_named_args(args.to_vec(), &spec.inputs, function)?
+    };
+    if read_stdin_arg {
+        substitute_stdin_arg(args)
+    } else {
+        Ok(args)
+    }
+}
+
+// Orders the arguments given as a JSON object by the names of the function parameters. String
+// values are parsed like --arg values, anything else like JSON.
+fn args_from_json(
+    args_json: &str,
+    inputs: &[ScSpecFunctionInputV0],
+    function: &str,
+) -> Result<Vec<Arg>, Error> {
+    let NamedArgs(named) = serde_json::from_str(args_json).map_err(Error::CannotParseArgsJson)?;
+    let names: Vec<String> = inputs.iter().map(|i| i.name.to_string_lossy()).collect();
+    let mut args: Vec<Option<Arg>> = vec![None; inputs.len()];
+    for (name, value) in named {
+        let i = names
+            .iter()
+            .position(|n| *n == name)
+            .ok_or_else(|| Error::UnknownArgument {
+                name: name.clone(),
+                function: function.to_string(),
+                valid: names.clone(),
+            })?;
+        if args[i].is_some() {
+            return Err(Error::DuplicateArgument(name));
+        }
+        args[i] = Some(Arg::Arg(match value {
+            Value::String(s) => s,
+            v => v.to_string(),
+        }));
+    }
+    args.into_iter()
+        .zip(names)
+        .map(|(arg, name)| {
+            arg.ok_or_else(|| Error::MissingArgument {
+                name,
+                function: function.to_string(),
+            })
+        })
+        .collect()
+}
+
+// A JSON object which keeps duplicate keys, so that they can be reported
+struct NamedArgs(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for NamedArgs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NamedArgsVisitor;
+        impl<'de> Visitor<'de> for NamedArgsVisitor {
+            type Value = NamedArgs;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a JSON object of arguments keyed by parameter name")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<NamedArgs, A::Error> {
+                let mut args = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    args.push(entry);
                 }
-                HostEvent::Debug(e) => eprintln!("debug: {}", e),
+                Ok(NamedArgs(args))
             }
         }
+        deserializer.deserialize_map(NamedArgsVisitor)
+    }
+}
 
-        snapshot::commit(state.1, ledger_info, &storage.map, &self.ledger_file).map_err(|e| {
-            Error::CannotCommitLedgerFile {
-                filepath: self.ledger_file.clone(),
-                error: e,
+// Whether the first topic of the event is one of the given symbols
+fn has_first_topic(event: &ContractEvent, symbols: &[String]) -> bool {
+    first_topic(event).map_or(false, |topic| symbols.contains(&topic))
+}
+
+fn first_topic(event: &ContractEvent) -> Option<String> {
+    let ContractEventBody::V0(ContractEventV0 { topics, .. }) = &event.body;
+    topics
+        .first()
+        .and_then(|topic| strval::to_string(topic).ok())
+}
+
+// Labels the data of an event with the fields of the spec struct named by its first topic, if
+// the data is a vec (or a single value) with as many items as the struct has fields
+fn typed_event(event: &ContractEvent, spec: &[ScSpecEntry]) -> Option<serde_json::Value> {
+    let ContractEventBody::V0(ContractEventV0 { topics, data }) = &event.body;
+    let name = topics.first().and_then(|t| strval::to_string(t).ok())?;
+    let fields = spec.iter().find_map(|entry| match entry {
+        ScSpecEntry::UdtStructV0(s) if s.name.to_string_lossy() == name => Some(&s.fields),
+        _ => None,
+    })?;
+    let values: Vec<&ScVal> = match data {
+        ScVal::Object(Some(ScObject::Vec(v))) if fields.len() != 1 => v.iter().collect(),
+        v => vec![v],
+    };
+    if values.len() != fields.len() {
+        return None;
+    }
+    let mut labeled = serde_json::Map::new();
+    for (field, value) in fields.iter().zip(values) {
+        labeled.insert(field.name.to_string_lossy(), strval::to_json(value).ok()?);
+    }
+    let topics = topics
+        .iter()
+        .map(strval::to_json)
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    Some(serde_json::json!({ "event": name, "topics": topics, "data": labeled }))
+}
+
+// Parses the hex of a SHA-256 hash, which unlike contract IDs must be given in full
+fn parse_wasm_hash(hash: &str) -> Result<[u8; 32], Error> {
+    <[u8; 32]>::from_hex(hash).map_err(|e| Error::CannotParseWasmHash {
+        hash: hash.to_string(),
+        error: e,
+    })
+}
+
+fn function_symbol(function: &str) -> Result<ScSymbol, Error> {
+    let max = ScSymbol::default().max_len();
+    function.try_into().map_err(|_| Error::FunctionNameTooLong {
+        function: function.to_string(),
+        length: function.len(),
+        max,
+    })
+}
+
+fn find_function_spec<'a>(
+    spec_entries: &'a [ScSpecEntry],
+    function: &str,
+    contract_id: [u8; 32],
+) -> Result<&'a ScSpecFunctionV0, Error> {
+    spec_entries
+        .iter()
+        .find_map(|e| {
+            if let ScSpecEntry::FunctionV0(f) = e {
+                if f.name.to_string_lossy() == function {
+                    return Some(f);
+                }
             }
-        })?;
-        Ok(())
+            None
+        })
+        .ok_or_else(|| Error::FunctionNotFoundInContractSpec {
+            function: function.to_string(),
+            contract_id: hex::encode(contract_id),
+            available: spec_entries
+                .iter()
+                .filter_map(|e| match e {
+                    ScSpecEntry::FunctionV0(f) => Some(f.name.to_string_lossy()),
+                    _ => None,
+                })
+                .collect(),
+        })
+}
+
+// Parses the (ordered) arguments according to the function spec, preceded by the contract ID and
+// the function name as the host expects them
+fn host_function_parameters(
+    contract_id: [u8; 32],
+    function: &str,
+    args: &[Arg],
+    spec_entries: &[ScSpecEntry],
+) -> Result<ScVec, Error> {
+    let spec = find_function_spec(spec_entries, function, contract_id)?;
+    let inputs = &spec.inputs;
+    if args.len() != inputs.len() {
+        return Err(Error::UnexpectedArgumentCount {
+            provided: args.len(),
+            expected: inputs.len(),
+            function: function.to_string(),
+            contract_id: hex::encode(contract_id),
+        });
+    }
+
+    let parsed_args = args
+        .iter()
+        .zip(inputs.iter())
+        .map(|(arg, input)| match arg {
+            Arg::ArgXdr(s) => ScVal::from_xdr_base64(s).map_err(|e| Error::CannotParseXdrArg {
+                arg: s.clone(),
+                error: e,
+            }),
+            Arg::Arg(s) => strval::from_string(s, &input.type_, spec_entries).map_err(|e| {
+                Error::CannotParseArg {
+                    arg: s.clone(),
+                    error: e,
+                }
+            }),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Add the contract ID and the function name to the arguments
+    let mut complete_args = vec![
+        ScVal::Object(Some(ScObject::Bytes(contract_id.try_into().unwrap()))),
+        ScVal::Symbol(function_symbol(function)?),
+    ];
+    complete_args.extend_from_slice(parsed_args.as_slice());
+    let complete_args_len = complete_args.len();
+
+    complete_args
+        .try_into()
+        .map_err(|_| Error::MaxNumberOfArgumentsReached {
+            current: complete_args_len,
+            maximum: ScVec::default().max_len(),
+        })
+}
+
+// Orders arguments given as name=value by the names of the function parameters. Arguments whose
+// text before the first '=' isn't the name of a parameter are positional.
+fn order_named_args(
+    args: Vec<Arg>,
+    inputs: &[ScSpecFunctionInputV0],
+    function: &str,
+) -> Result<Vec<Arg>, Error> {
+    let names: Vec<String> = inputs.iter().map(|i| i.name.to_string_lossy()).collect();
+    let split = |s: &str| -> Option<(usize, String)> {
+        let (name, value) = s.split_once('=')?;
+        let i = names.iter().position(|n| n == name)?;
+        Some((i, value.to_string()))
+    };
+    let named: Vec<Option<(usize, Arg)>> = args
+        .iter()
+        .map(|arg| match arg {
+            Arg::Arg(s) => split(s).map(|(i, v)| (i, Arg::Arg(v))),
+            Arg::ArgXdr(s) => split(s).map(|(i, v)| (i, Arg::ArgXdr(v))),
+        })
+        .collect();
+    match named.iter().filter(|n| n.is_some()).count() {
+        0 => return Ok(args),
+        n if n != args.len() => {
+            return Err(Error::MixedNamedAndPositionalArgs(function.to_string()));
+        }
+        _ => (),
+    }
+    let mut ordered: Vec<Option<Arg>> = vec![None; inputs.len()];
+    for (i, arg) in named.into_iter().flatten() {
+        if ordered[i].is_some() {
+            return Err(Error::DuplicateArgument(names[i].clone()));
+        }
+        ordered[i] = Some(arg);
+    }
+    ordered
+        .into_iter()
+        .zip(names.iter())
+        .map(|(arg, name)| {
+            arg.ok_or_else(|| Error::MissingArgument {
+                name: name.clone(),
+                function: function.to_string(),
+            })
+        })
+        .collect()
+}
+
+// Replaces the argument given as `-` (if any) with the contents of stdin
+fn substitute_stdin_arg(args: Vec<Arg>) -> Result<Vec<Arg>, Error> {
+    let is_stdin = |arg: &Arg| matches!(arg, Arg::Arg(s) | Arg::ArgXdr(s) if s == "-");
+    match args.iter().filter(|arg| is_stdin(arg)).count() {
+        0 => return Ok(args),
+        1 => (),
+        _ => return Err(Error::MultipleArgsFromStdin),
     }
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(Error::CannotReadArgsFromStdin)?;
+    // Drop the newline most tools terminate their output with
+    let input = input
+        .strip_suffix('\n')
+        .map_or(input.as_str(), |s| s.strip_suffix('\r').unwrap_or(s))
+        .to_string();
+    Ok(args
+        .into_iter()
+        .map(|arg| match arg {
+            Arg::Arg(s) if s == "-" => Arg::Arg(input.clone()),
+            Arg::ArgXdr(s) if s == "-" => Arg::ArgXdr(input.clone()),
+            arg => arg,
+        })
+        .collect())
+}
+
+// Keeps the first `max` bytes, elements or entries of the value, describing what was left out
+fn truncate_result(res: ScVal, max: usize) -> Result<(ScVal, Option<String>), XdrError> {
+    let (truncated, rest) = match &res {
+        ScVal::Object(Some(ScObject::Bytes(b))) if b.len() > max => (
+            ScObject::Bytes(b[..max].to_vec().try_into()?),
+            format!("{} more bytes", b.len() - max),
+        ),
+        ScVal::Object(Some(ScObject::Vec(v))) if v.0.len() > max => (
+            ScObject::Vec(ScVec(v.0[..max].to_vec().try_into()?)),
+            format!("{} more elements", v.0.len() - max),
+        ),
+        ScVal::Object(Some(ScObject::Map(m))) if m.0.len() > max => (
+            ScObject::Map(ScMap(m.0[..max].to_vec().try_into()?)),
+            format!("{} more entries", m.0.len() - max),
+        ),
+        _ => return Ok((res, None)),
+    };
+    Ok((ScVal::Object(Some(truncated)), Some(rest)))
+}
+
+// Contract events are written in their xdr form, debug events as their string
+fn write_events_file(path: &std::path::Path, events: &[HostEvent]) -> Result<(), io::Error> {
+    let mut contents = String::new();
+    for (i, event) in events.iter().enumerate() {
+        let event_json = match event {
+            HostEvent::Contract(e) => serde_json::json!({
+                "index": i,
+                "type": "contract",
+                "event": e,
+            }),
+            HostEvent::Debug(e) => serde_json::json!({
+                "index": i,
+                "type": "debug",
+                "event": e.to_string(),
+            }),
+        };
+        contents.push_str(&event_json.to_string());
+        contents.push('\n');
+    }
+    fs::write(path, contents)
+}
+
+fn print_footprint(read_only: &[LedgerKey], read_write: &[LedgerKey]) {
+    for key in read_only {
+        eprintln!("read-only: {}", utils::describe_ledger_key(key));
+    }
+    for key in read_write {
+        eprintln!("read-write: {}", utils::describe_ledger_key(key));
+    }
+}
+
+pub fn print_cost(budget: &Budget, sort: bool) {
+    eprintln!("Cpu Insns: {}", budget.get_cpu_insns_count());
+    eprintln!("Mem Bytes: {}", budget.get_mem_bytes_count());
+    let mut cost_types = CostType::variants().to_vec();
+    if sort {
+        cost_types.sort_by_key(|cost_type| std::cmp::Reverse(budget.get_input(*cost_type)));
+    }
+    for cost_type in cost_types {
+        eprintln!("Cost ({:?}): {}", cost_type, budget.get_input(cost_type));
+    }
+}
+
+/// JSON Schema of what `invoke --output json` prints on stdout: a single JSON document with the
+/// result, events and cost
+pub fn output_schema() -> Value {
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "soroban invoke --output json",
+        "type": "object",
+        "properties": {
+            "result": { "$ref": "#/$defs/result" },
+            "thenReadResult": { "$ref": "#/$defs/result" },
+            "events": { "$ref": "#/$defs/events" },
+            "cost": { "$ref": "#/$defs/cost" }
+        },
+        "$defs": {
+            "result": {
+                "description": "Value returned by the function (or by the --then-read one). An \
+                    ScVal in its XDR JSON form, keyed by the value's type, e.g. {\"u32\": 5}",
+                "type": ["object", "string"]
+            },
+            "events": {
+                "description": "Events emitted by the invocation, unless --quiet",
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["index", "type", "event"],
+                    "properties": {
+                        "index": {
+                            "description": "Position among all emitted events, before any \
+                                --event-topic filtering",
+                            "type": "integer",
+                            "minimum": 0
+                        },
+                        "type": { "enum": ["contract", "debug"] }
+                    },
+                    "oneOf": [
+                        {
+                            "properties": {
+                                "type": { "const": "contract" },
+                                "event": {
+                                    "anyOf": [
+                                        { "$ref": "#/$defs/typedEvent" },
+                                        {
+                                            "description": "ContractEvent in its XDR JSON form",
+                                            "type": "object"
+                                        }
+                                    ]
+                                }
+                            }
+                        },
+                        {
+                            "properties": {
+                                "type": { "const": "debug" },
+                                "event": { "type": "string" }
+                            }
+                        }
+                    ]
+                }
+            },
+            "typedEvent": {
+                "description": "Contract event labeled with the spec struct named by its first \
+                    topic, with --output-events-schema",
+                "type": "object",
+                "required": ["event", "topics", "data"],
+                "properties": {
+                    "event": { "type": "string" },
+                    "topics": { "type": "array" },
+                    "data": { "type": "object" }
+                }
+            },
+            "cost": {
+                "description": "Resources used by the invocation, with --cost",
+                "type": "object",
+                "required": ["cpuInsns", "memBytes"],
+                "properties": {
+                    "cpuInsns": { "type": "string", "pattern": "^[0-9]+$" },
+                    "memBytes": { "type": "string", "pattern": "^[0-9]+$" }
+                }
+            }
+        }
+    })
+}
+
+// The network doesn't expose its fee schedule yet, so the rates are given by the user
+fn estimate_fee(
+    base_fee: u32,
+    cost: &rpc::Cost,
+    cpu_insns_per_stroop: u64,
+    mem_bytes_per_stroop: u64,
+) -> Result<u32, Error> {
+    let cpu_insns = cost.cpu_insns.parse::<u64>()?;
+    let mem_bytes = cost.mem_bytes.parse::<u64>()?;
+    let resource_fee = cpu_insns / cpu_insns_per_stroop + mem_bytes / mem_bytes_per_stroop;
+    Ok(u32::try_from(u64::from(base_fee) + resource_fee).unwrap_or(u32::MAX))
 }
 
 fn build_invoke_contract_tx(
@@ -416,9 +2074,8 @@ fn build_invoke_contract_tx(
     footprint: Option<LedgerFootprint>,
     sequence: i64,
     fee: u32,
-    network_passphrase: &str,
-    key: &ed25519_dalek::Keypair,
-) -> Result<TransactionEnvelope, Error> {
+    source_account: [u8; 32],
+) -> Result<Transaction, Error> {
     // Use a default footprint if none provided
     let final_footprint = footprint.unwrap_or(LedgerFootprint {
         read_only: VecM::default(),
@@ -432,15 +2089,373 @@ fn build_invoke_contract_tx(
             footprint: final_footprint,
         }),
     };
-    let tx = Transaction {
-        source_account: MuxedAccount::Ed25519(Uint256(key.public.to_bytes())),
+    Ok(Transaction {
+        source_account: MuxedAccount::Ed25519(Uint256(source_account)),
         fee,
         seq_num: SequenceNumber(sequence),
         cond: Preconditions::None,
         memo: Memo::None,
         operations: vec![op].try_into()?,
         ext: TransactionExt::V0,
-    };
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_fee() {
+        let cost = rpc::Cost {
+            cpu_insns: "2500000".to_string(),
+            mem_bytes: "10240".to_string(),
+        };
+        assert_eq!(
+            estimate_fee(100, &cost, 10_000, 1_024).unwrap(),
+            100 + 250 + 10
+        );
+    }
 
-    Ok(utils::sign_transaction(key, &tx, network_passphrase)?)
+    #[test]
+    fn test_args_from_json() {
+        use soroban_env_host::xdr::ScSpecTypeDef;
+
+        let inputs = ["to", "amount"].map(|name| ScSpecFunctionInputV0 {
+            name: name.try_into().unwrap(),
+            type_: ScSpecTypeDef::U32,
+        });
+        let args = args_from_json(r#"{"amount":100,"to":"G"}"#, &inputs, "pay").unwrap();
+        assert!(
+            matches!(&args[..], [Arg::Arg(to), Arg::Arg(amount)] if to == "G" && amount == "100")
+        );
+
+        assert!(matches!(
+            args_from_json(r#"{"to":"G","to":"H","amount":1}"#, &inputs, "pay"),
+            Err(Error::DuplicateArgument(name)) if name == "to"
+        ));
+        assert!(matches!(
+            args_from_json(r#"{"to":"G","amount":1,"memo":2}"#, &inputs, "pay"),
+            Err(Error::UnknownArgument { name, .. }) if name == "memo"
+        ));
+        assert!(matches!(
+            args_from_json(r#"{"to":"G"}"#, &inputs, "pay"),
+            Err(Error::MissingArgument { name, .. }) if name == "amount"
+        ));
+    }
+
+    // Contract whose hello function writes a contract data entry (1 => 2), then traps
+    fn trapping_contract() -> Vec<u8> {
+        use soroban_env_host::xdr::ScEnvMetaEntry;
+
+        #[rustfmt::skip]
+        let mut wasm = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+            // Types: (i64, i64) -> i64 and () -> i64
+            0x01, 0x0b, 0x02, 0x60, 0x02, 0x7e, 0x7e, 0x01, 0x7e, 0x60, 0x00, 0x01, 0x7e,
+            // Imports: put_contract_data
+            0x02, 0x07, 0x01, 0x01, b'l', 0x01, b'_', 0x00, 0x00,
+            // Functions: hello
+            0x03, 0x02, 0x01, 0x01,
+            // Exports: hello
+            0x07, 0x09, 0x01, 0x05, b'h', b'e', b'l', b'l', b'o', 0x00, 0x01,
+            // Code: put_contract_data(1, 2), then unreachable
+            0x0a, 0x0c, 0x01, 0x0a, 0x00, 0x42, 0x02, 0x42, 0x04, 0x10, 0x00, 0x1a, 0x00, 0x0b,
+        ];
+        let name = b"contractenvmetav0";
+        let meta = ScEnvMetaEntry::ScEnvMetaKindInterfaceVersion(meta::INTERFACE_VERSION)
+            .to_xdr()
+            .unwrap();
+        wasm.push(0x00);
+        wasm.push(u8::try_from(1 + name.len() + meta.len()).unwrap());
+        wasm.push(u8::try_from(name.len()).unwrap());
+        wasm.extend_from_slice(name);
+        wasm.extend_from_slice(&meta);
+        wasm
+    }
+
+    /// Creates a temp dir named after the test with an empty ledger file in it, returning both
+    fn empty_ledger_file(test: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("soroban-cli-test-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let ledger_file = dir.join("ledger.json");
+        let ledger = r#"{"ledgerEntries":[],"protocolVersion":19,"sequenceNumber":7,"timestamp":35,"networkPassphrase":[],"baseReserve":0}"#;
+        fs::write(&ledger_file, ledger).unwrap();
+        (dir, ledger_file)
+    }
+
+    #[test]
+    fn test_failed_invocation_leaves_ledger_file_untouched() {
+        use clap::{CommandFactory, FromArgMatches};
+
+        let (dir, ledger_file) = empty_ledger_file("failed-invocation");
+        let wasm_file = dir.join("trap.wasm");
+        let spec_file = dir.join("trap.json");
+        fs::write(&wasm_file, trapping_contract()).unwrap();
+        let spec = [ScSpecEntry::FunctionV0(ScSpecFunctionV0 {
+            name: "hello".try_into().unwrap(),
+            inputs: VecM::default(),
+            outputs: VecM::default(),
+        })];
+        fs::write(&spec_file, serde_json::to_vec(&spec).unwrap()).unwrap();
+        let before = fs::read(&ledger_file).unwrap();
+
+        let args = [
+            "invoke",
+            "--id",
+            "1",
+            "--fn",
+            "hello",
+            "--wasm",
+            wasm_file.to_str().unwrap(),
+            "--spec-file",
+            spec_file.to_str().unwrap(),
+            "--ledger-file",
+            ledger_file.to_str().unwrap(),
+        ];
+        let matches = Cmd::command().get_matches_from(args);
+        let cmd = Cmd::from_arg_matches(&matches).unwrap();
+        // The contract is deployed and its entry written in the host's storage before it traps
+        let contract_id = utils::contract_id_from_str(&"1".to_string()).unwrap();
+        assert!(matches!(
+            cmd.run_in_sandbox(
+                contract_id,
+                &cmd.ordered_args(&matches),
+                Vec::new(),
+                None,
+                &mut JsonOutput::new()
+            ),
+            Err(Error::Host(_))
+        ));
+
+        assert_eq!(fs::read(&ledger_file).unwrap(), before);
+        assert!(!dir.join("ledger.json.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_command_line() {
+        use clap::FromArgMatches;
+
+        let matches = Cmd::command().get_matches_from([
+            "invoke",
+            "--id",
+            "1",
+            "--arg",
+            "a b",
+            "--fn",
+            "hello",
+            "--arg-xdr",
+            "AAAA",
+            "--rpc-server-url",
+            "http://localhost:8000",
+            "--secret-key",
+            "SBFGFF27Y64ZUGFAIG5AMJGQODZZKV2YQKAVUUN4HNE24XZXD2OEUVUP",
+            "--network-passphrase",
+            "Test",
+            "--sequence-strategy",
+            "local",
+        ]);
+        let cmd = Cmd::from_arg_matches(&matches).unwrap();
+        assert_eq!(
+            cmd.command_line(&matches, &cmd.ordered_args(&matches)),
+            "soroban invoke --id 1 --fn hello --rpc-server-url http://localhost:8000 \
+             --secret-key '<redacted>' --network-passphrase Test --sequence-strategy local \
+             --arg 'a b' --arg-xdr AAAA"
+        );
+    }
+
+    #[test]
+    fn test_truncate_result() {
+        let items: ScVec = vec![ScVal::U32(1), ScVal::U32(2), ScVal::U32(3)]
+            .try_into()
+            .unwrap();
+        let res = ScVal::Object(Some(ScObject::Vec(items)));
+
+        let (truncated, rest) = truncate_result(res.clone(), 2).unwrap();
+        let expected: ScVec = vec![ScVal::U32(1), ScVal::U32(2)].try_into().unwrap();
+        assert_eq!(truncated, ScVal::Object(Some(ScObject::Vec(expected))));
+        assert_eq!(rest.as_deref(), Some("1 more elements"));
+
+        assert_eq!(truncate_result(res.clone(), 3).unwrap(), (res, None));
+    }
+
+    #[test]
+    fn test_substitute_stdin_arg_allows_a_single_sentinel() {
+        let args = vec![Arg::Arg("-".to_string()), Arg::ArgXdr("-".to_string())];
+        assert!(matches!(
+            substitute_stdin_arg(args),
+            Err(Error::MultipleArgsFromStdin)
+        ));
+
+        let args = vec![Arg::Arg("1".to_string())];
+        assert!(matches!(
+            substitute_stdin_arg(args).unwrap().as_slice(),
+            [Arg::Arg(s)] if s == "1"
+        ));
+    }
+
+    #[test]
+    fn test_invoke_in_sandbox_without_contract() {
+        let (dir, ledger_file) = empty_ledger_file("invoke-in-sandbox");
+        let before = fs::read(&ledger_file).unwrap();
+
+        let args = [Arg::Arg("1".to_string())];
+        let opts = SandboxOptions {
+            ledger_file: ledger_file.clone(),
+            ..SandboxOptions::default()
+        };
+        assert!(invoke_in_sandbox([1; 32], "hello", &args, &opts).is_err());
+        assert_eq!(fs::read(&ledger_file).unwrap(), before);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_host_function_parameters() {
+        use soroban_env_host::xdr::ScSpecTypeDef;
+
+        let inputs = ["a", "b"].map(|name| ScSpecFunctionInputV0 {
+            name: name.try_into().unwrap(),
+            type_: ScSpecTypeDef::U32,
+        });
+        let spec = [ScSpecEntry::FunctionV0(ScSpecFunctionV0 {
+            name: "add".try_into().unwrap(),
+            inputs: inputs.to_vec().try_into().unwrap(),
+            outputs: vec![ScSpecTypeDef::U32].try_into().unwrap(),
+        })];
+        let arg = |s: &str| Arg::Arg(s.to_string());
+        let xdr_arg = |v: ScVal| Arg::ArgXdr(v.to_xdr_base64().unwrap());
+
+        let ok_cases = [
+            (vec![arg("1"), arg("2")], [1, 2]),
+            (vec![xdr_arg(ScVal::U32(3)), arg("4")], [3, 4]),
+            (vec![arg("5"), xdr_arg(ScVal::U32(6))], [5, 6]),
+        ];
+        for (args, expected) in ok_cases {
+            let params = host_function_parameters([0; 32], "add", &args, &spec).unwrap();
+            assert_eq!(params.len(), 4);
+            assert_eq!(params[2], ScVal::U32(expected[0]));
+            assert_eq!(params[3], ScVal::U32(expected[1]));
+        }
+
+        assert!(matches!(
+            host_function_parameters([0; 32], "add", &[arg("1")], &spec),
+            Err(Error::UnexpectedArgumentCount {
+                provided: 1,
+                expected: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            host_function_parameters([0; 32], "add", &[arg("x"), arg("1")], &spec),
+            Err(Error::CannotParseArg { arg, .. }) if arg == "x"
+        ));
+        assert!(matches!(
+            host_function_parameters([0; 32], "sub", &[], &spec),
+            Err(Error::FunctionNotFoundInContractSpec { function, .. }) if function == "sub"
+        ));
+    }
+
+    #[test]
+    fn test_order_named_args() {
+        use soroban_env_host::xdr::ScSpecTypeDef;
+
+        let inputs = ["to", "amount"].map(|name| ScSpecFunctionInputV0 {
+            name: name.try_into().unwrap(),
+            type_: ScSpecTypeDef::U32,
+        });
+        let arg = |s: &str| Arg::Arg(s.to_string());
+
+        let args = order_named_args(vec![arg("amount=100"), arg("to=G")], &inputs, "pay").unwrap();
+        assert!(
+            matches!(&args[..], [Arg::Arg(to), Arg::Arg(amount)] if to == "G" && amount == "100")
+        );
+
+        // Not a parameter name, so positional
+        let args = order_named_args(vec![arg("a=b"), arg("1")], &inputs, "pay").unwrap();
+        assert!(matches!(&args[..], [Arg::Arg(a), Arg::Arg(_)] if a == "a=b"));
+
+        assert!(matches!(
+            order_named_args(vec![arg("to=G"), arg("100")], &inputs, "pay"),
+            Err(Error::MixedNamedAndPositionalArgs(_))
+        ));
+        assert!(matches!(
+            order_named_args(vec![arg("to=G"), arg("to=H")], &inputs, "pay"),
+            Err(Error::DuplicateArgument(name)) if name == "to"
+        ));
+    }
+
+    #[test]
+    fn test_parse_wasm_hash() {
+        let hash = "ab".repeat(32);
+        assert_eq!(parse_wasm_hash(&hash).unwrap(), [0xab; 32]);
+        assert!(matches!(
+            parse_wasm_hash("1"),
+            Err(Error::CannotParseWasmHash {
+                error: FromHexError::OddLength | FromHexError::InvalidStringLength,
+                ..
+            })
+        ));
+        assert!(parse_wasm_hash(&"ab".repeat(33)).is_err());
+    }
+
+    #[test]
+    fn test_function_symbol() {
+        assert!(function_symbol("hello").is_ok());
+        let name = "a_function_name_longer_than_any_symbol";
+        assert!(matches!(
+            function_symbol(name),
+            Err(Error::FunctionNameTooLong { length, max, .. })
+                if length == name.len() && max == ScSymbol::default().max_len()
+        ));
+    }
+
+    #[test]
+    fn test_output_schema_cost() {
+        let schema = output_schema();
+        let cost = serde_json::to_value(rpc::Cost {
+            cpu_insns: "1".to_string(),
+            mem_bytes: "2".to_string(),
+        })
+        .unwrap();
+        let properties = schema["$defs"]["cost"]["properties"].as_object().unwrap();
+        let keys: Vec<&String> = cost.as_object().unwrap().keys().collect();
+        assert_eq!(properties.keys().collect::<Vec<_>>(), keys);
+    }
+
+    #[test]
+    fn test_json_output_is_one_document() {
+        let cmd = Cmd::parse_from(["invoke", "--id", "1", "--fn", "hello", "--output", "json"]);
+        let mut json = JsonOutput::new();
+        cmd.print_result(ScVal::U32(1), &mut json).unwrap();
+        cmd.print_result(ScVal::U32(2), &mut json).unwrap();
+        cmd.print_rpc_cost(
+            &rpc::Cost {
+                cpu_insns: "1".to_string(),
+                mem_bytes: "2".to_string(),
+            },
+            &mut json,
+        );
+        let mut stdout = Vec::new();
+        write_json_output(&mut stdout, &json).unwrap();
+
+        let documents = serde_json::Deserializer::from_slice(&stdout)
+            .into_iter::<Value>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(
+            documents[0],
+            serde_json::json!({
+                "result": { "u32": 1 },
+                "thenReadResult": { "u32": 2 },
+                "cost": { "cpuInsns": "1", "memBytes": "2" }
+            })
+        );
+
+        // Nothing gathered (e.g. with --quiet), nothing printed
+        let mut stdout = Vec::new();
+        write_json_output(&mut stdout, &JsonOutput::new()).unwrap();
+        assert!(stdout.is_empty());
+    }
 }
@@ -0,0 +1,34 @@
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::module_name_repetitions,
+    clippy::must_use_candidate
+)]
+
+pub mod address;
+pub mod bootstrap;
+pub mod completion;
+pub mod deploy;
+pub mod dump;
+pub mod gen;
+pub mod inspect;
+pub mod invoke;
+pub mod jsonrpc;
+pub mod list_contracts;
+pub mod network;
+pub mod network_id;
+pub mod read;
+pub mod replay;
+pub mod rpc;
+pub mod schema;
+pub mod seed_ledger;
+pub mod serve;
+pub mod snapshot;
+pub mod strkey;
+pub mod strval;
+pub mod submit;
+pub mod token;
+pub mod utils;
+pub mod verify;
+pub mod version;
+pub mod xdr;
@@ -0,0 +1,50 @@
+use std::{collections::BTreeMap, fmt::Debug};
+
+use clap::Parser;
+use soroban_env_host::xdr::LedgerKey;
+
+use crate::snapshot;
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Print the number of contract-data entries next to each contract ID
+    #[clap(long)]
+    count: bool,
+    /// File to persist ledger state
+    #[clap(long, parse(from_os_str), default_value(".soroban/ledger.json"))]
+    ledger_file: std::path::PathBuf,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("reading file {filepath}: {error}")]
+    CannotReadLedgerFile {
+        filepath: std::path::PathBuf,
+        error: snapshot::Error,
+    },
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        let state = snapshot::read(&self.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
+            filepath: self.ledger_file.clone(),
+            error: e,
+        })?;
+
+        let mut contracts: BTreeMap<[u8; 32], usize> = BTreeMap::new();
+        for key in state.entries.keys() {
+            if let LedgerKey::ContractData(data) = key {
+                *contracts.entry(data.contract_id.0).or_default() += 1;
+            }
+        }
+
+        for (contract_id, count) in contracts {
+            if self.count {
+                println!("{} {}", hex::encode(contract_id), count);
+            } else {
+                println!("{}", hex::encode(contract_id));
+            }
+        }
+        Ok(())
+    }
+}
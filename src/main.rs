@@ -1,21 +1,9 @@
 use clap::{AppSettings, CommandFactory, FromArgMatches, Parser, Subcommand};
-
-mod completion;
-mod deploy;
-mod gen;
-mod inspect;
-mod invoke;
-mod jsonrpc;
-mod network;
-mod read;
-mod rpc;
-mod serve;
-mod snapshot;
-mod strval;
-mod token;
-mod utils;
-mod version;
-mod xdr;
+use soroban_cli::{
+    address, bootstrap, completion, deploy, dump, gen, inspect, invoke, list_contracts, network_id,
+    read, replay, rpc, schema, seed_ledger, serve, strkey, submit, token, utils, verify, version,
+    xdr,
+};
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -27,6 +15,33 @@ mod xdr;
 )]
 #[clap(global_setting(AppSettings::DeriveDisplayOrder))]
 struct Root {
+    /// Fail any attempt to reach the network (e.g. for hermetic tests)
+    #[clap(long, alias = "no-network", global = true)]
+    offline: bool,
+    /// Fail rpc requests whose response is larger than this many bytes
+    #[clap(long, global = true, default_value_t = rpc::DEFAULT_MAX_RESPONSE_BYTES)]
+    max_response_bytes: u32,
+    /// Pretty-print JSON output
+    #[clap(long, global = true, conflicts_with = "json-compact")]
+    json_pretty: bool,
+    /// Print JSON output on a single line (default)
+    #[clap(long, global = true)]
+    json_compact: bool,
+    /// Format of the contract ids printed by deploy and token create/wrap
+    #[clap(long, global = true, arg_enum, default_value = "hex")]
+    id_format: utils::IdFormat,
+    /// Print how long each phase of the command took (reading the ledger file, running the
+    /// contract, each kind of rpc request, ...) to stderr
+    #[clap(long, global = true)]
+    profile: bool,
+    /// Log the requests sent to, and responses received from, the rpc server to stderr, and
+    /// print host errors in full
+    #[clap(long, global = true)]
+    verbose: bool,
+    /// JSON-RPC id of the first request sent to the rpc server (incremented for each further
+    /// request), for providers to correlate the requests of a command with
+    #[clap(long, global = true, default_value = "1")]
+    json_rpc_id_base: u64,
     #[clap(subcommand)]
     cmd: Cmd,
 }
@@ -39,17 +54,40 @@ enum Cmd {
     Inspect(inspect::Cmd),
     /// Print the current value of a contract-data ledger entry
     Read(read::Cmd),
+    /// List the IDs of the contracts with contract-data entries in the ledger file
+    ListContracts(list_contracts::Cmd),
+    /// Print all the contract-data entries of a contract in the ledger file as JSON
+    Dump(dump::Cmd),
+    /// Add funded accounts to the ledger file, e.g. for multi-party test scenarios
+    SeedLedger(seed_ledger::Cmd),
+    /// Fetch a contract's code and data entries from an rpc server into the ledger file
+    Bootstrap(bootstrap::Cmd),
     /// Run a local webserver for web app development and testing
     Serve(serve::Cmd),
     /// Wrap, create, and manage token contracts
     Token(token::Root),
     /// Deploy a WASM file as a contract
     Deploy(deploy::Cmd),
+    /// Send transactions signed elsewhere (e.g. written by --unsigned-out) to an rpc server
+    Submit(submit::Cmd),
+    /// Check the signatures of a signed transaction envelope before submitting it
+    Verify(verify::Cmd),
+    /// Re-execute a transaction envelope against the ledger file, reporting its result, events
+    /// and cost
+    Replay(replay::Cmd),
     /// Generate code client bindings for a contract
     Gen(gen::Cmd),
 
     /// Decode xdr
     Xdr(xdr::Cmd),
+    /// Print the JSON Schema of a command's `--output json` output
+    Schema(schema::Cmd),
+    /// Print the network id (hex SHA-256 of the network passphrase) used when signing
+    NetworkId(network_id::Cmd),
+    /// Convert a public or secret key between its strkey and hex representations
+    Strkey(strkey::Cmd),
+    /// Print the public key (G...) of a secret key
+    Address(address::Cmd),
 
     /// Print version information
     Version(version::Cmd),
@@ -68,6 +106,14 @@ enum CmdError {
     #[error(transparent)]
     Read(#[from] read::Error),
     #[error(transparent)]
+    ListContracts(#[from] list_contracts::Error),
+    #[error(transparent)]
+    Dump(#[from] dump::Error),
+    #[error(transparent)]
+    SeedLedger(#[from] seed_ledger::Error),
+    #[error(transparent)]
+    Bootstrap(#[from] bootstrap::Error),
+    #[error(transparent)]
     Serve(#[from] serve::Error),
     #[error(transparent)]
     Token(#[from] token::Error),
@@ -76,7 +122,28 @@ enum CmdError {
     #[error(transparent)]
     Deploy(#[from] deploy::Error),
     #[error(transparent)]
+    Submit(#[from] submit::Error),
+    #[error(transparent)]
+    Verify(#[from] verify::Error),
+    #[error(transparent)]
+    Replay(#[from] replay::Error),
+    #[error(transparent)]
     Xdr(#[from] xdr::Error),
+    #[error(transparent)]
+    Schema(#[from] schema::Error),
+    #[error(transparent)]
+    Strkey(#[from] strkey::Error),
+    #[error(transparent)]
+    Address(#[from] address::Error),
+}
+
+impl CmdError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            CmdError::Invoke(e) => e.exit_code(),
+            _ => 1,
+        }
+    }
 }
 
 async fn run(cmd: Cmd, matches: &mut clap::ArgMatches) -> Result<(), CmdError> {
@@ -86,12 +153,23 @@ async fn run(cmd: Cmd, matches: &mut clap::ArgMatches) -> Result<(), CmdError> {
             let (_, sub_arg_matches) = matches.remove_subcommand().unwrap();
             invoke.run(&sub_arg_matches).await?;
         }
-        Cmd::Read(read) => read.run()?,
+        Cmd::Read(read) => read.run().await?,
+        Cmd::ListContracts(list_contracts) => list_contracts.run()?,
+        Cmd::Dump(dump) => dump.run()?,
+        Cmd::SeedLedger(seed_ledger) => seed_ledger.run()?,
+        Cmd::Bootstrap(bootstrap) => bootstrap.run().await?,
         Cmd::Serve(serve) => serve.run().await?,
         Cmd::Token(token) => token.run().await?,
         Cmd::Gen(gen) => gen.run()?,
         Cmd::Deploy(deploy) => deploy.run().await?,
+        Cmd::Submit(submit) => submit.run().await?,
+        Cmd::Verify(verify) => verify.run()?,
+        Cmd::Replay(replay) => replay.run()?,
         Cmd::Xdr(xdr) => xdr.run()?,
+        Cmd::Schema(schema) => schema.run()?,
+        Cmd::NetworkId(network_id) => network_id.run(),
+        Cmd::Strkey(strkey) => strkey.run()?,
+        Cmd::Address(address) => address.run()?,
         Cmd::Version(version) => version.run(),
         Cmd::Completion(completion) => completion.run(&mut Root::command()),
     };
@@ -112,7 +190,26 @@ async fn main() {
         }
     };
 
-    if let Err(e) = run(root.cmd, &mut saved_matches).await {
+    if root.offline {
+        rpc::set_offline();
+    }
+    rpc::set_max_response_bytes(root.max_response_bytes);
+    rpc::set_json_rpc_id_base(root.json_rpc_id_base);
+    if root.json_pretty {
+        utils::set_json_pretty();
+    }
+    utils::set_id_format(root.id_format);
+    if root.profile {
+        utils::enable_profile();
+    }
+    if root.verbose {
+        rpc::set_verbose();
+        utils::set_verbose_errors();
+    }
+    let res = run(root.cmd, &mut saved_matches).await;
+    utils::print_profile();
+    if let Err(e) = res {
         eprintln!("error: {}", e);
+        std::process::exit(e.exit_code());
     }
 }
@@ -1 +1,4 @@
 pub static SANDBOX_NETWORK_PASSPHRASE: &str = "Local Sandbox Stellar Network ; September 2022";
+pub static FUTURENET_NETWORK_PASSPHRASE: &str = "Test SDF Future Network ; October 2022";
+pub static TESTNET_NETWORK_PASSPHRASE: &str = "Test SDF Network ; September 2015";
+pub static PUBLIC_NETWORK_PASSPHRASE: &str = "Public Global Stellar Network ; September 2015";
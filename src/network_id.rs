@@ -0,0 +1,48 @@
+use clap::{ArgEnum, Parser};
+use std::fmt::Debug;
+
+use crate::{network, utils};
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Network passphrase to hash
+    #[clap(
+        long = "network-passphrase",
+        conflicts_with = "network",
+        required_unless_present = "network"
+    )]
+    network_passphrase: Option<String>,
+    /// Well-known network to use the passphrase of
+    #[clap(long, arg_enum)]
+    network: Option<Network>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ArgEnum)]
+pub enum Network {
+    Sandbox,
+    Futurenet,
+    Testnet,
+    Public,
+}
+
+impl Network {
+    fn passphrase(self) -> &'static str {
+        match self {
+            Network::Sandbox => network::SANDBOX_NETWORK_PASSPHRASE,
+            Network::Futurenet => network::FUTURENET_NETWORK_PASSPHRASE,
+            Network::Testnet => network::TESTNET_NETWORK_PASSPHRASE,
+            Network::Public => network::PUBLIC_NETWORK_PASSPHRASE,
+        }
+    }
+}
+
+impl Cmd {
+    pub fn run(&self) {
+        let passphrase = match (&self.network_passphrase, self.network) {
+            (Some(passphrase), _) => passphrase.as_str(),
+            (None, Some(network)) => network.passphrase(),
+            (None, None) => unreachable!("clap requires one of them"),
+        };
+        println!("{}", hex::encode(utils::network_id(passphrase)));
+    }
+}
@@ -4,7 +4,6 @@ use std::{
 };
 
 use clap::{ArgEnum, Parser};
-use hex::FromHexError;
 use soroban_env_host::{
     xdr::{
         self, ContractDataEntry, Error as XdrError, LedgerEntryData, LedgerKey,
@@ -14,6 +13,7 @@ use soroban_env_host::{
 };
 
 use crate::{
+    rpc::{self, Client},
     snapshot,
     strval::{self, StrValError},
     utils,
@@ -34,8 +34,17 @@ pub struct Cmd {
     #[clap(long, arg_enum, default_value("string"))]
     output: Output,
     /// File to persist ledger state
-    #[clap(long, parse(from_os_str), default_value(".soroban/ledger.json"))]
+    #[clap(
+        long,
+        parse(from_os_str),
+        default_value(".soroban/ledger.json"),
+        conflicts_with = "rpc-server-url"
+    )]
     ledger_file: std::path::PathBuf,
+
+    /// RPC server endpoint to read the entry from (requires --key or --key-xdr)
+    #[clap(long)]
+    rpc_server_url: Option<String>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ArgEnum)]
@@ -62,8 +71,10 @@ pub enum Error {
     #[error("cannot parse contract ID {contract_id}: {error}")]
     CannotParseContractId {
         contract_id: String,
-        error: FromHexError,
+        error: utils::ParseContractIdError,
     },
+    #[error("a --key or --key-xdr is required when reading from the rpc server")]
+    KeyRequiredWithRpc,
     #[error("cannot print result {result:?}: {error}")]
     CannotPrintResult { result: ScVal, error: StrValError },
     #[error("cannot print result {result:?}: {error}")]
@@ -77,15 +88,15 @@ pub enum Error {
     CannotPrintFlush { error: io::Error },
     #[error("xdr processing error: {0}")]
     Xdr(#[from] XdrError),
-    #[error(transparent)]
-    // TODO: the Display impl of host errors is pretty user-unfriendly
-    //       (it just calls Debug). I think we can do better than that
+    #[error("host error: {}", utils::HostErrorDisplay(.0))]
     Host(#[from] HostError),
+    #[error(transparent)]
+    Rpc(#[from] rpc::Error),
 }
 
 impl Cmd {
     #[allow(clippy::too_many_lines)]
-    pub fn run(&self) -> Result<(), Error> {
+    pub async fn run(&self) -> Result<(), Error> {
         let contract_id: [u8; 32] =
             utils::contract_id_from_str(&self.contract_id).map_err(|e| {
                 Error::CannotParseContractId {
@@ -95,7 +106,7 @@ impl Cmd {
             })?;
         let key = if let Some(key) = &self.key {
             Some(
-                strval::from_string(key, &ScSpecTypeDef::Symbol).map_err(|e| {
+                strval::from_string(key, &ScSpecTypeDef::Symbol, &[]).map_err(|e| {
                     Error::CannotParseKey {
                         key: key.clone(),
                         error: e,
@@ -113,11 +124,65 @@ impl Cmd {
             None
         };
 
+        let entries = if let Some(rpc_server_url) = &self.rpc_server_url {
+            let key = key.ok_or(Error::KeyRequiredWithRpc)?;
+            let client = Client::new(rpc_server_url);
+            let data = client
+                .get_contract_data(&hex::encode(contract_id), key.clone())
+                .await?;
+            vec![ContractDataEntry {
+                contract_id: xdr::Hash(contract_id),
+                key,
+                val: data.val,
+            }]
+        } else {
+            self.read_from_ledger_file(contract_id, key)?
+        };
+
+        let mut out = csv::Writer::from_writer(stdout());
+        for data in entries {
+            let output = match self.output {
+                Output::String => [
+                    strval::to_string(&data.key).map_err(|e| Error::CannotPrintResult {
+                        result: data.key.clone(),
+                        error: e,
+                    })?,
+                    strval::to_string(&data.val).map_err(|e| Error::CannotPrintResult {
+                        result: data.val.clone(),
+                        error: e,
+                    })?,
+                ],
+                Output::Json => [
+                    utils::to_json_string(&data.key).map_err(|e| Error::CannotPrintJsonResult {
+                        result: data.key.clone(),
+                        error: e,
+                    })?,
+                    utils::to_json_string(&data.val).map_err(|e| Error::CannotPrintJsonResult {
+                        result: data.val.clone(),
+                        error: e,
+                    })?,
+                ],
+                Output::Xdr => [data.key.to_xdr_base64()?, data.val.to_xdr_base64()?],
+            };
+            out.write_record(output)
+                .map_err(|e| Error::CannotPrintAsCsv { error: e })?;
+        }
+        out.flush()
+            .map_err(|e| Error::CannotPrintFlush { error: e })?;
+
+        Ok(())
+    }
+
+    fn read_from_ledger_file(
+        &self,
+        contract_id: [u8; 32],
+        key: Option<ScVal>,
+    ) -> Result<Vec<ContractDataEntry>, Error> {
         let state = snapshot::read(&self.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
             filepath: self.ledger_file.clone(),
             error: e,
         })?;
-        let ledger_entries = state.1;
+        let ledger_entries = state.entries;
 
         let contract_id = xdr::Hash(contract_id);
         let entries: Vec<ContractDataEntry> = if let Some(key) = key {
@@ -154,42 +219,6 @@ impl Cmd {
                 })
                 .collect()
         };
-
-        let mut out = csv::Writer::from_writer(stdout());
-        for data in entries {
-            let output = match self.output {
-                Output::String => [
-                    strval::to_string(&data.key).map_err(|e| Error::CannotPrintResult {
-                        result: data.key.clone(),
-                        error: e,
-                    })?,
-                    strval::to_string(&data.val).map_err(|e| Error::CannotPrintResult {
-                        result: data.val.clone(),
-                        error: e,
-                    })?,
-                ],
-                Output::Json => [
-                    serde_json::to_string_pretty(&data.key).map_err(|e| {
-                        Error::CannotPrintJsonResult {
-                            result: data.key.clone(),
-                            error: e,
-                        }
-                    })?,
-                    serde_json::to_string_pretty(&data.val).map_err(|e| {
-                        Error::CannotPrintJsonResult {
-                            result: data.val.clone(),
-                            error: e,
-                        }
-                    })?,
-                ],
-                Output::Xdr => [data.key.to_xdr_base64()?, data.val.to_xdr_base64()?],
-            };
-            out.write_record(output)
-                .map_err(|e| Error::CannotPrintAsCsv { error: e })?;
-        }
-        out.flush()
-            .map_err(|e| Error::CannotPrintFlush { error: e })?;
-
-        Ok(())
+        Ok(entries)
     }
 }
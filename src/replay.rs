@@ -0,0 +1,206 @@
+use std::{
+    fmt::Debug,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use soroban_env_host::{
+    budget::Budget,
+    events::HostEvent,
+    storage::{AccessType, Storage},
+    xdr::{
+        AccountId, Error as XdrError, FeeBumpTransactionInnerTx, HostFunction,
+        InvokeHostFunctionOp, LedgerFootprint, LedgerKey, MuxedAccount, Operation, OperationBody,
+        PublicKey, ReadXdr, ScVal, TransactionEnvelope,
+    },
+    Host, HostError,
+};
+
+use crate::{
+    invoke, snapshot,
+    strval::{self, StrValError},
+    utils,
+};
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Transaction envelope (base64 xdr) with a single invoke host function operation, `-`
+    /// reads it from stdin
+    #[clap(long = "envelope-xdr")]
+    envelope_xdr: String,
+    /// File to read the ledger state from
+    #[clap(long, parse(from_os_str), default_value(".soroban/ledger.json"))]
+    ledger_file: PathBuf,
+    /// Write the resulting ledger state back to the ledger file
+    #[clap(long)]
+    commit: bool,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("reading transaction envelope from stdin: {0}")]
+    CannotReadEnvelopeFromStdin(io::Error),
+    #[error("parsing transaction envelope: {0}")]
+    CannotParseEnvelope(XdrError),
+    #[error("transaction must contain exactly one invoke host function operation")]
+    UnsupportedTransaction,
+    #[error("reading file {filepath}: {error}")]
+    CannotReadLedgerFile {
+        filepath: PathBuf,
+        error: snapshot::Error,
+    },
+    #[error("committing file {filepath}: {error}")]
+    CannotCommitLedgerFile {
+        filepath: PathBuf,
+        error: snapshot::Error,
+    },
+    #[error("cannot print result {result:?}: {error}")]
+    CannotPrintResult { result: ScVal, error: StrValError },
+    #[error("host error: {}", utils::HostErrorDisplay(.0))]
+    Host(#[from] HostError),
+    #[error("cannot finish the host session, it is still referenced elsewhere")]
+    CannotFinishHost,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        let input = if self.envelope_xdr == "-" {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(Error::CannotReadEnvelopeFromStdin)?;
+            input
+        } else {
+            self.envelope_xdr.clone()
+        };
+        let envelope = TransactionEnvelope::from_xdr_base64(input.trim().to_string())
+            .map_err(Error::CannotParseEnvelope)?;
+        let (source_account, op) = invoke_host_function_op(&envelope)?;
+
+        let state = snapshot::read(&self.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
+            filepath: self.ledger_file.clone(),
+            error: e,
+        })?;
+        let snap = snapshot::snap_from(&state);
+        let storage = Storage::with_recording_footprint(snap);
+        let h = Host::with_storage_and_budget(storage, Budget::default());
+        h.set_source_account(source_account);
+        let mut ledger_info = state.info.clone();
+        ledger_info.sequence_number += 1;
+        ledger_info.timestamp += 5;
+        h.set_ledger_info(ledger_info.clone());
+
+        let function = invoked_function(&op);
+        let res = h.invoke_function(op.function, op.parameters.clone())?;
+
+        let (storage, budget, events) = h.try_finish().map_err(|_h| Error::CannotFinishHost)?;
+
+        invoke::print_cost(&budget, false);
+        // The sandbox records the footprint instead of enforcing the declared one, so report
+        // where they differ rather than failing like the network would
+        for (key, access) in storage.footprint.0.iter() {
+            if let Some(mismatch) = footprint_mismatch(&op.footprint, key, *access) {
+                eprintln!(
+                    "footprint: {}: {}",
+                    utils::describe_ledger_key(key),
+                    mismatch
+                );
+            }
+        }
+        for (i, event) in events.0.iter().enumerate() {
+            eprint!("#{}: ", i);
+            match event {
+                HostEvent::Contract(e) => {
+                    eprintln!("event: {}", utils::to_json_string(&e).unwrap());
+                }
+                HostEvent::Debug(e) => {
+                    eprintln!("{}", utils::describe_debug_event(e, function.as_deref()))
+                }
+            };
+        }
+
+        let res_str = strval::to_string(&res).map_err(|e| Error::CannotPrintResult {
+            result: res,
+            error: e,
+        })?;
+        println!("{}", res_str);
+
+        if self.commit {
+            snapshot::commit(
+                state.entries,
+                ledger_info,
+                state.notes,
+                None,
+                &storage.map,
+                &self.ledger_file,
+            )
+            .map_err(|e| Error::CannotCommitLedgerFile {
+                filepath: self.ledger_file.clone(),
+                error: e,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the only operation of the transaction, which must invoke a host function, along with
+/// the account it runs as
+fn invoke_host_function_op(
+    envelope: &TransactionEnvelope,
+) -> Result<(AccountId, InvokeHostFunctionOp), Error> {
+    let (tx_source_account, ops): (MuxedAccount, &[Operation]) = match envelope {
+        TransactionEnvelope::TxV0(e) => (
+            MuxedAccount::Ed25519(e.tx.source_account_ed25519.clone()),
+            &e.tx.operations,
+        ),
+        TransactionEnvelope::Tx(e) => (e.tx.source_account.clone(), &e.tx.operations),
+        TransactionEnvelope::TxFeeBump(e) => {
+            let FeeBumpTransactionInnerTx::Tx(inner) = &e.tx.inner_tx;
+            (inner.tx.source_account.clone(), &inner.tx.operations)
+        }
+    };
+    match ops {
+        [Operation {
+            source_account,
+            body: OperationBody::InvokeHostFunction(op),
+        }] => {
+            let source_account = source_account.clone().unwrap_or(tx_source_account);
+            Ok((account_id(source_account), op.clone()))
+        }
+        _ => Err(Error::UnsupportedTransaction),
+    }
+}
+
+/// Name of the contract function the operation invokes, if it invokes one
+fn invoked_function(op: &InvokeHostFunctionOp) -> Option<String> {
+    match (&op.function, op.parameters.get(1)) {
+        (HostFunction::InvokeContract, Some(ScVal::Symbol(function))) => {
+            Some(function.to_string_lossy())
+        }
+        _ => None,
+    }
+}
+
+fn account_id(muxed_account: MuxedAccount) -> AccountId {
+    AccountId(PublicKey::PublicKeyTypeEd25519(match muxed_account {
+        MuxedAccount::Ed25519(a) => a,
+        MuxedAccount::MuxedEd25519(a) => a.ed25519,
+    }))
+}
+
+/// Describes how an access recorded while replaying disagrees with the declared footprint, if it
+/// does
+fn footprint_mismatch(
+    declared: &LedgerFootprint,
+    key: &LedgerKey,
+    access: AccessType,
+) -> Option<&'static str> {
+    if declared.read_write.contains(key) {
+        None
+    } else if declared.read_only.contains(key) {
+        (access == AccessType::ReadWrite).then_some("written but declared read-only")
+    } else {
+        Some("accessed but not declared")
+    }
+}
@@ -0,0 +1,79 @@
+use clap::ArgEnum;
+use std::{collections::BTreeMap, fs, path::Path};
+
+use super::{Client, Error};
+
+/// File the `local` strategy keeps the last used sequence number of each account in
+pub const LOCAL_SEQUENCE_FILE: &str = ".soroban/sequences.json";
+
+/// How the sequence number of the source account is obtained when building transactions.
+///
+/// - `fetch` asks the rpc server before every command. It's always correct, as long as no other
+///   transaction from the same account is in flight at the same time.
+/// - `local` fetches once and then keeps counting in [`LOCAL_SEQUENCE_FILE`], saving a round
+///   trip per transaction in a batch. It goes stale (and transactions fail with a bad sequence)
+///   as soon as the account is used from anywhere else; delete the file to resync.
+/// - `provided` uses `--sequence` as is and never touches the network, leaving correctness
+///   entirely to the caller.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ArgEnum)]
+pub enum SequenceStrategy {
+    Fetch,
+    Local,
+    Provided,
+}
+
+impl Client {
+    /// Returns the current sequence number of the account, according to the given strategy
+    pub async fn account_sequence(
+        &self,
+        account_id: &str,
+        strategy: SequenceStrategy,
+        provided: Option<i64>,
+    ) -> Result<i64, Error> {
+        match strategy {
+            SequenceStrategy::Provided => provided.ok_or(Error::SequenceRequired),
+            SequenceStrategy::Local => {
+                if let Some(sequence) = read_local_sequences()?.get(account_id) {
+                    return Ok(*sequence);
+                }
+                self.fetch_sequence(account_id).await
+            }
+            SequenceStrategy::Fetch => self.fetch_sequence(account_id).await,
+        }
+    }
+
+    async fn fetch_sequence(&self, account_id: &str) -> Result<i64, Error> {
+        let account = self.get_account(account_id).await?;
+        account
+            .sequence
+            .parse()
+            .map_err(|_| Error::InvalidSequence(account.sequence))
+    }
+}
+
+/// Records the last sequence number used by the account, if the strategy keeps track of it
+pub fn record_sequence(
+    strategy: SequenceStrategy,
+    account_id: &str,
+    sequence: i64,
+) -> Result<(), Error> {
+    if strategy != SequenceStrategy::Local {
+        return Ok(());
+    }
+    let mut sequences = read_local_sequences()?;
+    sequences.insert(account_id.to_string(), sequence);
+    let path = Path::new(LOCAL_SEQUENCE_FILE);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(Error::SequenceFile)?;
+    }
+    let contents = serde_json::to_vec_pretty(&sequences).map_err(Error::CannotParseSequenceFile)?;
+    fs::write(path, contents).map_err(Error::SequenceFile)
+}
+
+fn read_local_sequences() -> Result<BTreeMap<String, i64>, Error> {
+    match fs::read(LOCAL_SEQUENCE_FILE) {
+        Ok(contents) => serde_json::from_slice(&contents).map_err(Error::CannotParseSequenceFile),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(Error::SequenceFile(e)),
+    }
+}
@@ -0,0 +1,32 @@
+use clap::{ArgEnum, Parser};
+use std::fmt::Debug;
+
+use crate::{invoke, utils};
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Command to print the `--output json` schema of
+    #[clap(arg_enum, default_value = "invoke")]
+    command: Command,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ArgEnum)]
+pub enum Command {
+    Invoke,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("printing the schema: {0}")]
+    CannotPrintSchema(#[from] serde_json::Error),
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        let schema = match self.command {
+            Command::Invoke => invoke::output_schema(),
+        };
+        println!("{}", utils::to_json_string(&schema)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::Debug;
+
+use clap::Parser;
+use rand::Rng;
+use soroban_env_host::xdr::{AccountId, PublicKey, Uint256};
+use stellar_strkey::{StrkeyPrivateKeyEd25519, StrkeyPublicKeyEd25519};
+
+use crate::snapshot;
+use crate::utils;
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Number of accounts to create with random keys, printing their public and secret keys
+    #[clap(long, default_value = "0")]
+    count: u32,
+    /// Public key (G...) of an account to create, can be repeated
+    #[clap(long = "account")]
+    accounts: Vec<StrkeyPublicKeyEd25519>,
+    /// Balance (in stroops) of the created accounts
+    #[clap(long, default_value = "100000000000")]
+    balance: i64,
+    /// File to persist ledger state
+    #[clap(long, parse(from_os_str), default_value(".soroban/ledger.json"))]
+    ledger_file: std::path::PathBuf,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("reading file {filepath}: {error}")]
+    CannotReadLedgerFile {
+        filepath: std::path::PathBuf,
+        error: snapshot::Error,
+    },
+    #[error("committing file {filepath}: {error}")]
+    CannotCommitLedgerFile {
+        filepath: std::path::PathBuf,
+        error: snapshot::Error,
+    },
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        let mut state =
+            snapshot::read(&self.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
+                filepath: self.ledger_file.clone(),
+                error: e,
+            })?;
+
+        for account in &self.accounts {
+            if !utils::ensure_account_entry(
+                &mut state.entries,
+                &account_id(account.0),
+                self.balance,
+            ) {
+                let public_strkey = account.to_string();
+                eprintln!("Account {} already exists, leaving it as is", public_strkey);
+            }
+        }
+        for _ in 0..self.count {
+            let seed = rand::thread_rng().gen::<[u8; 32]>();
+            let secret = ed25519_dalek::SecretKey::from_bytes(&seed).unwrap();
+            let public = ed25519_dalek::PublicKey::from(&secret).to_bytes();
+            utils::ensure_account_entry(&mut state.entries, &account_id(public), self.balance);
+            let public_strkey = StrkeyPublicKeyEd25519(public).to_string();
+            let secret_strkey = StrkeyPrivateKeyEd25519(seed).to_string();
+            println!("{} {}", public_strkey, secret_strkey);
+        }
+
+        snapshot::commit(
+            state.entries,
+            state.info,
+            state.notes,
+            None,
+            [],
+            &self.ledger_file,
+        )
+        .map_err(|e| Error::CannotCommitLedgerFile {
+            filepath: self.ledger_file.clone(),
+            error: e,
+        })
+    }
+}
+
+fn account_id(public_key: [u8; 32]) -> AccountId {
+    AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(public_key)))
+}
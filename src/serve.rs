@@ -1,8 +1,7 @@
 use std::collections::HashMap;
-use std::{convert::Infallible, fmt::Debug, io, net::SocketAddr, path::PathBuf, rc::Rc, sync::Arc};
+use std::{convert::Infallible, fmt::Debug, io, net::SocketAddr, path::PathBuf, sync::Arc};
 
 use clap::Parser;
-use hex::FromHexError;
 use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
 use soroban_env_host::xdr::{AccountId, LedgerFootprint, MuxedAccount, Operation, PublicKey};
@@ -11,8 +10,8 @@ use soroban_env_host::{
     storage::{AccessType, Footprint, Storage},
     xdr::{
         self, Error as XdrError, FeeBumpTransactionInnerTx, HostFunction, LedgerEntryData,
-        LedgerKey, LedgerKeyContractData, OperationBody, ReadXdr, ScHostStorageErrorCode, ScObject,
-        ScStatus, ScVal, TransactionEnvelope, WriteXdr,
+        LedgerKey, LedgerKeyContractData, OperationBody, ReadXdr, ScObject, ScVal,
+        TransactionEnvelope, WriteXdr,
     },
     Host, HostError,
 };
@@ -46,14 +45,16 @@ pub enum Error {
     Xdr(#[from] XdrError),
     #[error("host")]
     Host(#[from] HostError),
+    #[error("cannot finish host")]
+    CannotFinishHost,
     #[error("snapshot")]
     Snapshot(#[from] snapshot::Error),
     #[error("serde")]
     Serde(#[from] serde_json::Error),
     #[error("unsupported transaction: {message}")]
     UnsupportedTransaction { message: String },
-    #[error("hex")]
-    FromHex(#[from] FromHexError),
+    #[error("contract id")]
+    ContractId(#[from] utils::ParseContractIdError),
     #[error("unknownmethod")]
     UnknownMethod,
 }
@@ -203,9 +204,7 @@ fn get_contract_data(
     let contract_id: [u8; 32] = utils::contract_id_from_str(&contract_id_hex.to_string())?;
     let key = ScVal::from_xdr_base64(key_xdr)?;
 
-    let snap = Rc::new(snapshot::Snap {
-        ledger_entries: state.1,
-    });
+    let snap = snapshot::snap_from(&state);
     let mut storage = Storage::with_recording_footprint(snap);
     let ledger_entry = storage.get(&LedgerKey::ContractData(LedgerKeyContractData {
         contract_id: xdr::Hash(contract_id),
@@ -334,15 +333,13 @@ fn execute_transaction(
     // Initialize storage and host
     let state = snapshot::read(ledger_file)?;
 
-    let snap = Rc::new(snapshot::Snap {
-        ledger_entries: state.1.clone(),
-    });
+    let snap = snapshot::snap_from(&state);
     let storage = Storage::with_recording_footprint(snap);
     let h = Host::with_storage_and_budget(storage, Budget::default());
 
     h.set_source_account(source_account);
 
-    let mut ledger_info = state.0.clone();
+    let mut ledger_info = state.info.clone();
     ledger_info.sequence_number += 1;
     ledger_info.timestamp += 5;
     h.set_ledger_info(ledger_info.clone());
@@ -351,11 +348,7 @@ fn execute_transaction(
 
     let res = h.invoke_function(HostFunction::InvokeContract, args.try_into()?)?;
 
-    let (storage, budget, _) = h.try_finish().map_err(|_h| {
-        HostError::from(ScStatus::HostStorageError(
-            ScHostStorageErrorCode::UnknownError,
-        ))
-    })?;
+    let (storage, budget, _) = h.try_finish().map_err(|_h| Error::CannotFinishHost)?;
 
     // Calculate the budget usage
     let mut cost = serde_json::Map::new();
@@ -389,7 +382,14 @@ fn execute_transaction(
     };
 
     if commit {
-        snapshot::commit(state.1, ledger_info, &storage.map, ledger_file)?;
+        snapshot::commit(
+            state.entries,
+            ledger_info,
+            state.notes,
+            None,
+            &storage.map,
+            ledger_file,
+        )?;
     }
 
     Ok(json!({
@@ -534,7 +534,8 @@ async fn send_transaction(
                             json!({
                                 "id": id,
                                 "status": "success",
-                                "results": vec![result],
+                                "results": result["results"],
+                                "cost": result["cost"],
                             })
                         }
                         Err(err) => {
@@ -1,4 +1,11 @@
-use std::{fs::create_dir_all, fs::File, io, iter::IntoIterator};
+use std::{
+    fs::File,
+    fs::{create_dir_all, remove_file, rename},
+    io,
+    iter::IntoIterator,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use soroban_env_host::{
     im_rc::OrdMap,
@@ -25,6 +32,20 @@ pub struct Snap {
     pub ledger_entries: OrdMap<LedgerKey, LedgerEntry>,
 }
 
+/// Ledger state read from a ledger file
+pub struct LedgerState {
+    pub info: LedgerInfo,
+    pub entries: OrdMap<LedgerKey, LedgerEntry>,
+    pub notes: Vec<Note>,
+}
+
+/// Builds a snapshot source over the ledger entries of the state, to back host storage
+pub fn snap_from(state: &LedgerState) -> Rc<Snap> {
+    Rc::new(Snap {
+        ledger_entries: state.entries.clone(),
+    })
+}
+
 pub fn get_default_ledger_info() -> LedgerInfo {
     LedgerInfo {
         protocol_version: 19,
@@ -35,6 +56,27 @@ pub fn get_default_ledger_info() -> LedgerInfo {
     }
 }
 
+/// Free-form note recorded in the ledger file when committing, for debugging sequences of
+/// sandbox operations. Notes are not visible to contracts.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Note {
+    pub note: String,
+    /// Wall-clock time of the commit, in seconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl Note {
+    pub fn new(note: &str) -> Self {
+        Self {
+            note: note.to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct SerializableState {
@@ -44,6 +86,8 @@ struct SerializableState {
     timestamp: u64,
     network_passphrase: Vec<u8>,
     base_reserve: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<Note>,
 }
 
 impl SnapshotSource for Snap {
@@ -59,24 +103,24 @@ impl SnapshotSource for Snap {
 }
 
 // Ledger file format is the default serde JSON representation of VecM<(LedgerKey, LedgerEntry)>
-pub fn read(
-    input_file: &std::path::PathBuf,
-) -> Result<(LedgerInfo, OrdMap<LedgerKey, LedgerEntry>), Error> {
-    let mut entries = OrdMap::new();
-
+pub fn read(input_file: &std::path::PathBuf) -> Result<LedgerState, Error> {
     let mut file = match File::open(input_file) {
         Ok(f) => f,
         Err(e) => {
             //File doesn't exist, so treat this as an empty database and the file will be created later
             if e.kind() == io::ErrorKind::NotFound {
-                return Ok((get_default_ledger_info(), entries));
+                return Ok(LedgerState {
+                    info: get_default_ledger_info(),
+                    entries: OrdMap::new(),
+                    notes: Vec::new(),
+                });
             }
             return Err(Error::Io(e));
         }
     };
 
     let state: SerializableState = serde_json::from_reader(&mut file)?;
-    entries = state.ledger_entries.iter().cloned().collect();
+    let entries = state.ledger_entries.iter().cloned().collect();
     let info = LedgerInfo {
         protocol_version: state.protocol_version,
         sequence_number: state.sequence_number,
@@ -84,12 +128,18 @@ pub fn read(
         network_passphrase: state.network_passphrase,
         base_reserve: state.base_reserve,
     };
-    Ok((info, entries))
+    Ok(LedgerState {
+        info,
+        entries,
+        notes: state.notes,
+    })
 }
 
 pub fn commit<'a, I>(
     mut new_state: OrdMap<LedgerKey, LedgerEntry>,
     ledger_info: LedgerInfo,
+    mut notes: Vec<Note>,
+    note: Option<&str>,
     storage_map: I,
     output_file: &std::path::PathBuf,
 ) -> Result<(), Error>
@@ -103,7 +153,6 @@ where
         }
     }
 
-    let file = File::create(output_file)?;
     for (lk, ole) in storage_map {
         if let Some(le) = ole {
             new_state.insert(lk.clone(), le.clone());
@@ -112,6 +161,10 @@ where
         }
     }
 
+    if let Some(note) = note {
+        notes.push(Note::new(note));
+    }
+
     let vec_new_state: VecM<(LedgerKey, LedgerEntry)> =
         new_state.into_iter().collect::<Vec<_>>().try_into()?;
 
@@ -122,8 +175,23 @@ where
         timestamp: ledger_info.timestamp,
         network_passphrase: ledger_info.network_passphrase,
         base_reserve: ledger_info.base_reserve,
+        notes,
     };
-    serde_json::to_writer(&file, &output)?;
+    // Write to a temporary file and move it in place, so that the ledger file is either fully
+    // updated or left untouched
+    let mut tmp_file = output_file.clone().into_os_string();
+    tmp_file.push(".tmp");
+    let file = File::create(&tmp_file)?;
+    let res = write_state(&file, &output).and_then(|()| Ok(rename(&tmp_file, output_file)?));
+    if res.is_err() {
+        // Best effort, the error worth reporting is the one of the write
+        let _ = remove_file(&tmp_file);
+    }
+    res
+}
 
+fn write_state(file: &File, state: &SerializableState) -> Result<(), Error> {
+    serde_json::to_writer(file, state)?;
+    file.sync_all()?;
     Ok(())
 }
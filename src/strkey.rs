@@ -0,0 +1,83 @@
+use std::fmt::Debug;
+
+use clap::{ArgEnum, Parser};
+use stellar_strkey::{StrkeyPrivateKeyEd25519, StrkeyPublicKeyEd25519};
+
+use crate::utils;
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Strkey (G..., S... or C...) to convert to hex, or 32-byte hex to convert to a strkey
+    value: String,
+    /// Kind of strkey to convert hex input to
+    #[clap(long, arg_enum, default_value = "public-key")]
+    kind: Kind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ArgEnum)]
+pub enum Kind {
+    /// Public key (G...)
+    PublicKey,
+    /// Secret key (S...)
+    SecretKey,
+    /// Contract id (C...)
+    Contract,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("cannot parse strkey {0}")]
+    CannotParseStrkey(String),
+    #[error("cannot parse {0} as a strkey or as 32-byte hex")]
+    CannotParseValue(String),
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        println!("{}", convert(&self.value, self.kind)?);
+        Ok(())
+    }
+}
+
+fn convert(value: &str, kind: Kind) -> Result<String, Error> {
+    Ok(match value.chars().next() {
+        Some('G') => StrkeyPublicKeyEd25519::from_string(value)
+            .map(|k| hex::encode(k.0))
+            .map_err(|_| Error::CannotParseStrkey(value.to_string()))?,
+        Some('S') => StrkeyPrivateKeyEd25519::from_string(value)
+            .map(|k| hex::encode(k.0))
+            .map_err(|_| Error::CannotParseStrkey(value.to_string()))?,
+        // Hex can start with a C too, so anything else is taken as hex
+        Some('C') if utils::contract_id_from_strkey(value).is_some() => {
+            hex::encode(utils::contract_id_from_strkey(value).unwrap())
+        }
+        _ => {
+            let bytes = utils::contract_id_from_str(&value.to_string())
+                .map_err(|_| Error::CannotParseValue(value.to_string()))?;
+            match kind {
+                Kind::PublicKey => StrkeyPublicKeyEd25519(bytes).to_string(),
+                Kind::SecretKey => StrkeyPrivateKeyEd25519(bytes).to_string(),
+                Kind::Contract => utils::contract_strkey(&bytes),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_round_trip() {
+        let hex = "00".repeat(31) + "2a";
+        for kind in [Kind::PublicKey, Kind::SecretKey, Kind::Contract] {
+            let strkey = convert(&hex, kind).unwrap();
+            assert_eq!(convert(&strkey, kind).unwrap(), hex, "{:?}", kind);
+        }
+        assert!(convert(&hex, Kind::Contract).unwrap().starts_with('C'));
+        assert!(matches!(
+            convert(&("C".to_string() + &"A".repeat(55)), Kind::Contract),
+            Err(Error::CannotParseValue(_))
+        ));
+    }
+}
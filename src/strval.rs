@@ -1,11 +1,12 @@
 use serde_json::Value;
-use std::{error::Error, fmt::Display, str::FromStr};
+use sha2::{Digest, Sha256};
+use std::{error::Error, fmt::Display, fs, io, str::FromStr};
 
 use num_bigint::{BigInt, Sign};
 use soroban_env_host::xdr::{
-    AccountId, Error as XdrError, PublicKey, ScBigInt, ScMap, ScMapEntry, ScObject, ScSpecTypeDef,
-    ScSpecTypeMap, ScSpecTypeOption, ScSpecTypeTuple, ScSpecTypeVec, ScStatic, ScVal, ScVec,
-    Uint256, VecM,
+    AccountId, Error as XdrError, PublicKey, ScBigInt, ScMap, ScMapEntry, ScObject, ScSpecEntry,
+    ScSpecTypeDef, ScSpecTypeMap, ScSpecTypeOption, ScSpecTypeTuple, ScSpecTypeUdt, ScSpecTypeVec,
+    ScSpecUdtUnionV0, ScStatic, ScVal, ScVec, Uint256, VecM,
 };
 
 use stellar_strkey::StrkeyPublicKeyEd25519;
@@ -16,8 +17,12 @@ use crate::utils;
 pub enum StrValError {
     UnknownError,
     InvalidValue,
+    UnknownUnionCase { case: String, valid: Vec<String> },
     Xdr(XdrError),
     Serde(serde_json::Error),
+    CannotReadFile { path: String, error: io::Error },
+    UnknownDecodeType(String),
+    DuplicateMapKey,
 }
 
 impl Error for StrValError {
@@ -32,8 +37,21 @@ impl Display for StrValError {
         match self {
             Self::UnknownError => write!(f, "an unknown error occurred")?,
             Self::InvalidValue => write!(f, "value is not parseable to type")?,
+            Self::UnknownUnionCase { case, valid } => write!(
+                f,
+                "unknown case {}, expected one of: {}",
+                case,
+                valid.join(", ")
+            )?,
             Self::Serde(e) => write!(f, "{}", e)?,
             Self::Xdr(e) => write!(f, "{}", e)?,
+            Self::CannotReadFile { path, error } => write!(f, "reading file {}: {}", path, error)?,
+            Self::UnknownDecodeType(t) => write!(
+                f,
+                "unknown type {}, expected string, hex, account_id, vec<type> or option<type>",
+                t
+            )?,
+            Self::DuplicateMapKey => write!(f, "map has the same key more than once")?,
         };
         Ok(())
     }
@@ -51,7 +69,7 @@ impl From<()> for StrValError {
     }
 }
 
-pub fn from_string(s: &str, t: &ScSpecTypeDef) -> Result<ScVal, StrValError> {
+pub fn from_string(s: &str, t: &ScSpecTypeDef, spec: &[ScSpecEntry]) -> Result<ScVal, StrValError> {
     let val: ScVal = match t {
         // These ones have special processing when they're the top-level args. This is so we don't
         // need extra quotes around string args.
@@ -61,12 +79,25 @@ pub fn from_string(s: &str, t: &ScSpecTypeDef) -> Result<ScVal, StrValError> {
                 .map_err(|_| StrValError::InvalidValue)?,
         ),
 
+        // A 32-byte hash might be given as the hash of a file's contents, e.g. a wasm hash
+        ScSpecTypeDef::BytesN(bytes) if bytes.n == 32 && s.starts_with("sha256:") => {
+            let path = &s["sha256:".len()..];
+            let contents = fs::read(path).map_err(|e| StrValError::CannotReadFile {
+                path: path.to_string(),
+                error: e,
+            })?;
+            let hash: [u8; 32] = Sha256::digest(contents).into();
+            ScVal::Object(Some(ScObject::Bytes(
+                hash.try_into().map_err(StrValError::Xdr)?,
+            )))
+        }
+
         // This might either be a json array of u8s, or just the raw utf-8 bytes
         ScSpecTypeDef::Bytes | ScSpecTypeDef::BytesN(_) => {
             match serde_json::from_str(s) {
                 // First, see if it is a json array
-                Ok(v @ (Value::Array(_) | Value::String(_))) => from_json(&v, t)?,
-                _ => from_json(&Value::String(s.to_string()), t)?,
+                Ok(v @ (Value::Array(_) | Value::String(_))) => from_json(&v, t, spec)?,
+                _ => from_json(&Value::String(s.to_string()), t, spec)?,
             }
         }
 
@@ -74,9 +105,10 @@ pub fn from_string(s: &str, t: &ScSpecTypeDef) -> Result<ScVal, StrValError> {
         ScSpecTypeDef::BigInt => {
             if let Ok(Value::String(raw)) = serde_json::from_str(s) {
                 // First, see if it is a json string, strip the quotes and recurse
-                from_string(&raw, &ScSpecTypeDef::BigInt)?
+                from_string(&raw, &ScSpecTypeDef::BigInt, spec)?
             } else {
-                let big = BigInt::from_str(s).map_err(|_| StrValError::InvalidValue)?;
+                let big =
+                    BigInt::from_str(&expand_integer(s)?).map_err(|_| StrValError::InvalidValue)?;
                 let (sign, bytes) = big.to_bytes_be();
                 let b: VecM<u8, 256_000_u32> = bytes.try_into().map_err(StrValError::Xdr)?;
                 ScVal::Object(Some(ScObject::BigInt(match sign {
@@ -87,16 +119,91 @@ pub fn from_string(s: &str, t: &ScSpecTypeDef) -> Result<ScVal, StrValError> {
             }
         }
 
+        // Unions might be given as Case(payload), or as a bare case name without quotes
+        ScSpecTypeDef::Udt(udt) if find_union(udt, spec).is_some() => {
+            if let Some((case, payload)) = split_union_case(s) {
+                let union = find_union(udt, spec).ok_or(StrValError::UnknownError)?;
+                let case_type = find_union_case(union, case)?;
+                let payload = match case_type {
+                    Some(case_type) => Some(from_string(payload, case_type, spec)?),
+                    None if payload.is_empty() => None,
+                    None => return Err(StrValError::InvalidValue),
+                };
+                union_to_scval(case, payload)?
+            } else {
+                match serde_json::from_str(s) {
+                    Ok(v) => from_json(&v, t, spec)?,
+                    Err(_) => from_json(&Value::String(s.to_string()), t, spec)?,
+                }
+            }
+        }
+
+        // Might be written with underscores or in scientific notation, e.g. 10_000_000 or 1e7
+        ScSpecTypeDef::U32 | ScSpecTypeDef::I32 | ScSpecTypeDef::U64 | ScSpecTypeDef::I64 => {
+            serde_json::from_str(&expand_integer(s)?)
+                .map_err(StrValError::Serde)
+                .and_then(|raw| from_json(&raw, t, spec))?
+        }
+
         // For all others we just use the json parser
         _ => serde_json::from_str(s)
             .map_err(StrValError::Serde)
-            .and_then(|raw| from_json(&raw, t))?,
+            .and_then(|raw| from_json(&raw, t, spec))?,
     };
     Ok(val)
 }
 
+// Expands an integer written with underscores or in scientific notation into plain digits, failing
+// if it isn't a whole number (e.g. 1e-2)
+fn expand_integer(s: &str) -> Result<String, StrValError> {
+    let s = s.replace('_', "");
+    let (mantissa, exponent) = match s.split_once(|c| c == 'e' || c == 'E') {
+        Some((mantissa, exponent)) => (mantissa, exponent),
+        None => return Ok(s),
+    };
+    let exponent: i64 = exponent.parse().map_err(|_| StrValError::InvalidValue)?;
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty()
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(StrValError::InvalidValue);
+    }
+
+    // The value is digits * 10^shift
+    let mut digits = format!("{}{}", whole, fraction);
+    let shift = exponent - i64::try_from(fraction.len()).map_err(|_| StrValError::InvalidValue)?;
+    let zeros = usize::try_from(shift.unsigned_abs()).map_err(|_| StrValError::InvalidValue)?;
+    if shift > 1_000 {
+        return Err(StrValError::InvalidValue);
+    } else if shift >= 0 {
+        digits.push_str(&"0".repeat(zeros));
+    } else {
+        let keep = digits
+            .len()
+            .checked_sub(zeros)
+            .ok_or(StrValError::InvalidValue)?;
+        if digits[keep..].chars().any(|c| c != '0') {
+            return Err(StrValError::InvalidValue);
+        }
+        digits.truncate(keep);
+    }
+    let digits = digits.trim_start_matches('0');
+    Ok(if digits.is_empty() {
+        "0".to_string()
+    } else {
+        format!("{}{}", sign, digits)
+    })
+}
+
 #[allow(clippy::too_many_lines)]
-pub fn from_json(v: &Value, t: &ScSpecTypeDef) -> Result<ScVal, StrValError> {
+pub fn from_json(v: &Value, t: &ScSpecTypeDef, spec: &[ScSpecEntry]) -> Result<ScVal, StrValError> {
     let val: ScVal = match (t, v) {
         // Boolean parsing
         (ScSpecTypeDef::Bool, Value::Bool(true)) => ScVal::Static(ScStatic::True),
@@ -107,17 +214,19 @@ pub fn from_json(v: &Value, t: &ScSpecTypeDef) -> Result<ScVal, StrValError> {
             let ScSpecTypeVec { element_type } = &**elem;
             let parsed: Result<Vec<ScVal>, StrValError> = raw
                 .iter()
-                .map(|item| -> Result<ScVal, StrValError> { from_json(item, element_type) })
+                .map(|item| -> Result<ScVal, StrValError> { from_json(item, element_type, spec) })
                 .collect();
             let converted: ScVec = parsed?.try_into().map_err(StrValError::Xdr)?;
             ScVal::Object(Some(ScObject::Vec(converted)))
         }
 
         // Number parsing
-        (ScSpecTypeDef::BigInt, Value::String(s)) => from_string(s, &ScSpecTypeDef::BigInt)?,
-        (ScSpecTypeDef::BigInt, Value::Number(n)) => {
-            from_json(&Value::String(format!("{}", n)), &ScSpecTypeDef::BigInt)?
-        }
+        (ScSpecTypeDef::BigInt, Value::String(s)) => from_string(s, &ScSpecTypeDef::BigInt, spec)?,
+        (ScSpecTypeDef::BigInt, Value::Number(n)) => from_json(
+            &Value::String(format!("{}", n)),
+            &ScSpecTypeDef::BigInt,
+            spec,
+        )?,
         (ScSpecTypeDef::I32, Value::Number(n)) => ScVal::I32(
             n.as_i64()
                 .ok_or(StrValError::InvalidValue)?
@@ -147,8 +256,8 @@ pub fn from_json(v: &Value, t: &ScSpecTypeDef) -> Result<ScVal, StrValError> {
             let parsed: Result<Vec<ScMapEntry>, StrValError> = raw
                 .iter()
                 .map(|(k, v)| -> Result<ScMapEntry, StrValError> {
-                    let key = from_string(k, key_type)?;
-                    let val = from_json(v, value_type)?;
+                    let key = from_string(k, key_type, spec)?;
+                    let val = from_json(v, value_type, spec)?;
                     Ok(ScMapEntry { key, val })
                 })
                 .collect();
@@ -208,7 +317,7 @@ pub fn from_json(v: &Value, t: &ScSpecTypeDef) -> Result<ScVal, StrValError> {
         (ScSpecTypeDef::Option(elem), v) => {
             let ScSpecTypeOption { value_type } = &**elem;
             ScVal::Object(Some(
-                from_json(v, value_type)?
+                from_json(v, value_type, spec)?
                     .try_into()
                     .map_err(|_| StrValError::InvalidValue)?,
             ))
@@ -223,23 +332,93 @@ pub fn from_json(v: &Value, t: &ScSpecTypeDef) -> Result<ScVal, StrValError> {
             let parsed: Result<Vec<ScVal>, StrValError> = raw
                 .iter()
                 .zip(value_types.iter())
-                .map(|(item, t)| from_json(item, t))
+                .map(|(item, t)| from_json(item, t, spec))
                 .collect();
             let converted: ScVec = parsed?.try_into().map_err(StrValError::Xdr)?;
             ScVal::Object(Some(ScObject::Vec(converted)))
         }
 
+        // Union parsing, e.g. "Case" or {"Case": payload}
+        (ScSpecTypeDef::Udt(udt), raw) if find_union(udt, spec).is_some() => {
+            let union = find_union(udt, spec).ok_or(StrValError::UnknownError)?;
+            let (case, payload) = match raw {
+                Value::String(case) => (case.as_str(), None),
+                Value::Object(o) if o.len() == 1 => {
+                    let (case, payload) = o.iter().next().ok_or(StrValError::InvalidValue)?;
+                    (case.as_str(), Some(payload))
+                }
+                _ => return Err(StrValError::InvalidValue),
+            };
+            let payload = match (find_union_case(union, case)?, payload) {
+                (Some(case_type), Some(payload)) => Some(from_json(payload, case_type, spec)?),
+                (None, None | Some(Value::Null)) => None,
+                _ => return Err(StrValError::InvalidValue),
+            };
+            union_to_scval(case, payload)?
+        }
+
         // TODO: Implement the rest of these
         // ScSpecTypeDef::Bitset => {},
         // ScSpecTypeDef::Status => {},
         // ScSpecTypeDef::Result(Box<ScSpecTypeResult>) => {},
         // ScSpecTypeDef::Set(Box<ScSpecTypeSet>) => {},
-        // ScSpecTypeDef::Udt(ScSpecTypeUdt) => {},
+        // ScSpecTypeDef::Udt(ScSpecTypeUdt) => {}, (structs and enums)
         (_, raw) => serde_json::from_value(raw.clone()).map_err(StrValError::Serde)?,
     };
     Ok(val)
 }
 
+fn find_union<'a>(udt: &ScSpecTypeUdt, spec: &'a [ScSpecEntry]) -> Option<&'a ScSpecUdtUnionV0> {
+    spec.iter().find_map(|entry| match entry {
+        ScSpecEntry::UdtUnionV0(union) if union.name.as_slice() == udt.name.as_slice() => {
+            Some(union)
+        }
+        _ => None,
+    })
+}
+
+// Returns the payload type of the named case, if the case has one
+fn find_union_case<'a>(
+    union: &'a ScSpecUdtUnionV0,
+    case: &str,
+) -> Result<Option<&'a ScSpecTypeDef>, StrValError> {
+    union
+        .cases
+        .iter()
+        .find(|c| c.name.to_string_lossy() == case)
+        .map(|c| c.type_.as_ref())
+        .ok_or_else(|| StrValError::UnknownUnionCase {
+            case: case.to_string(),
+            valid: union
+                .cases
+                .iter()
+                .map(|c| c.name.to_string_lossy())
+                .collect(),
+        })
+}
+
+// Splits "Case(payload)" into its case name and payload
+fn split_union_case(s: &str) -> Option<(&str, &str)> {
+    let (case, rest) = s.split_once('(')?;
+    let payload = rest.strip_suffix(')')?;
+    if case.is_empty() || !case.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some((case, payload))
+}
+
+// Unions are represented as a vec of the case name symbol followed by the payload (if any)
+fn union_to_scval(case: &str, payload: Option<ScVal>) -> Result<ScVal, StrValError> {
+    let mut items = vec![ScVal::Symbol(
+        case.as_bytes()
+            .try_into()
+            .map_err(|_| StrValError::InvalidValue)?,
+    )];
+    items.extend(payload);
+    let converted: ScVec = items.try_into().map_err(StrValError::Xdr)?;
+    Ok(ScVal::Object(Some(ScObject::Vec(converted))))
+}
+
 pub fn to_string(v: &ScVal) -> Result<String, StrValError> {
     #[allow(clippy::match_same_arms)]
     Ok(match v {
@@ -321,3 +500,294 @@ pub fn to_json(v: &ScVal) -> Result<Value, StrValError> {
     };
     Ok(val)
 }
+
+/// Type to render a value as instead of its own, e.g. bytes holding UTF-8 text as a string
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeAs {
+    /// Bytes or a symbol as a UTF-8 string
+    String,
+    /// Bytes as a hex string
+    Hex,
+    /// 32 bytes as a public key (G...)
+    AccountId,
+    /// Each item of a vec as the inner type
+    Vec(Box<DecodeAs>),
+    /// The value of an option as the inner type, or null
+    Option(Box<DecodeAs>),
+}
+
+impl FromStr for DecodeAs {
+    type Err = StrValError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let inner = |prefix: &str| s.strip_prefix(prefix).and_then(|s| s.strip_suffix('>'));
+        if let Some(inner) = inner("vec<") {
+            return Ok(DecodeAs::Vec(Box::new(inner.parse()?)));
+        }
+        if let Some(inner) = inner("option<") {
+            return Ok(DecodeAs::Option(Box::new(inner.parse()?)));
+        }
+        match s {
+            "string" => Ok(DecodeAs::String),
+            "hex" => Ok(DecodeAs::Hex),
+            "account_id" => Ok(DecodeAs::AccountId),
+            _ => Err(StrValError::UnknownDecodeType(s.to_string())),
+        }
+    }
+}
+
+impl Display for DecodeAs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeAs::String => write!(f, "string"),
+            DecodeAs::Hex => write!(f, "hex"),
+            DecodeAs::AccountId => write!(f, "account_id"),
+            DecodeAs::Vec(inner) => write!(f, "vec<{}>", inner),
+            DecodeAs::Option(inner) => write!(f, "option<{}>", inner),
+        }
+    }
+}
+
+/// Like [`to_string`], but renders the value as the given type instead of its own
+pub fn to_string_as(v: &ScVal, t: &DecodeAs) -> Result<String, StrValError> {
+    // Top-level strings are printed without the wrapping quotes, like symbols in to_string
+    match to_json_as(v, t)? {
+        Value::String(s) => Ok(s),
+        json => serde_json::to_string(&json).map_err(StrValError::Serde),
+    }
+}
+
+pub fn to_json_as(v: &ScVal, t: &DecodeAs) -> Result<Value, StrValError> {
+    Ok(match (t, v) {
+        (DecodeAs::String, ScVal::Object(Some(ScObject::Bytes(b)))) => Value::String(
+            std::str::from_utf8(b.as_slice())
+                .map_err(|_| StrValError::InvalidValue)?
+                .to_string(),
+        ),
+        (DecodeAs::String, ScVal::Symbol(_))
+        | (DecodeAs::AccountId, ScVal::Object(Some(ScObject::AccountId(_)))) => to_json(v)?,
+        (DecodeAs::Hex, ScVal::Object(Some(ScObject::Bytes(b)))) => {
+            Value::String(hex::encode(b.as_slice()))
+        }
+        (DecodeAs::AccountId, ScVal::Object(Some(ScObject::Bytes(b)))) => {
+            let key: [u8; 32] = b
+                .as_slice()
+                .try_into()
+                .map_err(|_| StrValError::InvalidValue)?;
+            Value::String(StrkeyPublicKeyEd25519(key).to_string())
+        }
+        (DecodeAs::Vec(inner), ScVal::Object(Some(ScObject::Vec(items)))) => Value::Array(
+            items
+                .iter()
+                .map(|item| to_json_as(item, inner))
+                .collect::<Result<_, _>>()?,
+        ),
+        (DecodeAs::Option(_), ScVal::Object(None) | ScVal::Static(ScStatic::Void)) => Value::Null,
+        (DecodeAs::Option(inner), v) => to_json_as(v, inner)?,
+        _ => return Err(StrValError::InvalidValue),
+    })
+}
+
+/// Returns the value with the entries of every (nested) map sorted by key, so that values which
+/// only differ in the order of their map entries compare equal. Fails if a map then has the same
+/// key twice, i.e. keys which only differed in the order of their inner maps.
+pub fn canonicalize(v: &ScVal) -> Result<ScVal, StrValError> {
+    Ok(match v {
+        ScVal::Object(Some(ScObject::Vec(items))) => {
+            let items = items
+                .iter()
+                .map(canonicalize)
+                .collect::<Result<Vec<_>, _>>()?;
+            ScVal::Object(Some(ScObject::Vec(
+                items.try_into().map_err(StrValError::Xdr)?,
+            )))
+        }
+        ScVal::Object(Some(ScObject::Map(entries))) => {
+            let entries = entries
+                .iter()
+                .map(|ScMapEntry { key, val }| {
+                    Ok(ScMapEntry {
+                        key: canonicalize(key)?,
+                        val: canonicalize(val)?,
+                    })
+                })
+                .collect::<Result<Vec<_>, StrValError>>()?;
+            // Keys which only differ in the order of their inner maps are now equal
+            ScVal::Object(Some(ScObject::Map(
+                ScMap::sorted_from(entries).map_err(|_| StrValError::DuplicateMapKey)?,
+            )))
+        }
+        _ => v.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_env_host::xdr::ScSpecTypeBytesN;
+
+    #[test]
+    fn test_integer_scientific_notation() {
+        assert_eq!(
+            from_string("1e7", &ScSpecTypeDef::U64, &[]).unwrap(),
+            ScVal::Object(Some(ScObject::U64(10_000_000)))
+        );
+        assert_eq!(
+            from_string("1.5e3", &ScSpecTypeDef::I32, &[]).unwrap(),
+            ScVal::I32(1_500)
+        );
+        assert_eq!(
+            from_string("10_000_000", &ScSpecTypeDef::U32, &[]).unwrap(),
+            ScVal::U32(10_000_000)
+        );
+        assert!(from_string("1e-2", &ScSpecTypeDef::U64, &[]).is_err());
+    }
+
+    #[test]
+    fn test_union() {
+        use soroban_env_host::xdr::{ScSpecTypeUdt, ScSpecUdtUnionCaseV0};
+
+        let spec = [ScSpecEntry::UdtUnionV0(ScSpecUdtUnionV0 {
+            name: "Color".try_into().unwrap(),
+            cases: vec![
+                ScSpecUdtUnionCaseV0 {
+                    name: "Red".try_into().unwrap(),
+                    type_: None,
+                },
+                ScSpecUdtUnionCaseV0 {
+                    name: "Rgb".try_into().unwrap(),
+                    type_: Some(ScSpecTypeDef::U32),
+                },
+            ]
+            .try_into()
+            .unwrap(),
+        })];
+        let t = ScSpecTypeDef::Udt(ScSpecTypeUdt {
+            name: "Color".try_into().unwrap(),
+        });
+        let union =
+            |items: Vec<ScVal>| ScVal::Object(Some(ScObject::Vec(items.try_into().unwrap())));
+        let red = union(vec![ScVal::Symbol("Red".try_into().unwrap())]);
+        let rgb = union(vec![
+            ScVal::Symbol("Rgb".try_into().unwrap()),
+            ScVal::U32(7),
+        ]);
+
+        assert_eq!(from_string("Red", &t, &spec).unwrap(), red);
+        assert_eq!(from_string("\"Red\"", &t, &spec).unwrap(), red);
+        assert_eq!(from_string("Rgb(7)", &t, &spec).unwrap(), rgb);
+        assert_eq!(from_string(r#"{"Rgb":7}"#, &t, &spec).unwrap(), rgb);
+        assert_eq!(
+            from_json(&serde_json::json!({ "Rgb": 7 }), &t, &spec).unwrap(),
+            rgb
+        );
+
+        // A payload given to a unit case, or missing for a case with one
+        assert!(from_string("Red(7)", &t, &spec).is_err());
+        assert!(from_string(r#"{"Red":7}"#, &t, &spec).is_err());
+        assert!(from_string("Rgb", &t, &spec).is_err());
+
+        match from_string("Blue", &t, &spec) {
+            Err(StrValError::UnknownUnionCase { case, valid }) => {
+                assert_eq!(case, "Blue");
+                assert_eq!(valid, ["Red", "Rgb"]);
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+        assert!(matches!(
+            from_string("Blue(1)", &t, &spec),
+            Err(StrValError::UnknownUnionCase { .. })
+        ));
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let map = |entries: Vec<(u32, ScVal)>| {
+            let entries: Vec<ScMapEntry> = entries
+                .into_iter()
+                .map(|(key, val)| ScMapEntry {
+                    key: ScVal::U32(key),
+                    val,
+                })
+                .collect();
+            ScVal::Object(Some(ScObject::Map(entries.try_into().unwrap())))
+        };
+        let inner = map(vec![(2, ScVal::U32(20)), (1, ScVal::U32(10))]);
+        let sorted_inner = map(vec![(1, ScVal::U32(10)), (2, ScVal::U32(20))]);
+        let unsorted = map(vec![(9, inner.clone()), (3, ScVal::Static(ScStatic::True))]);
+        let sorted = map(vec![
+            (3, ScVal::Static(ScStatic::True)),
+            (9, sorted_inner.clone()),
+        ]);
+
+        assert_ne!(unsorted, sorted);
+        assert_eq!(
+            canonicalize(&unsorted).unwrap(),
+            canonicalize(&sorted).unwrap()
+        );
+        assert_eq!(canonicalize(&unsorted).unwrap(), sorted);
+        assert_ne!(
+            canonicalize(&map(vec![(1, ScVal::U32(10))])).unwrap(),
+            canonicalize(&map(vec![(1, ScVal::U32(11))])).unwrap()
+        );
+
+        // Two keys that only differ in the order of their inner maps
+        let entries: Vec<ScMapEntry> = [inner, sorted_inner]
+            .into_iter()
+            .map(|key| ScMapEntry {
+                key,
+                val: ScVal::U32(0),
+            })
+            .collect();
+        let colliding = ScVal::Object(Some(ScObject::Map(entries.try_into().unwrap())));
+        assert!(matches!(
+            canonicalize(&colliding),
+            Err(StrValError::DuplicateMapKey)
+        ));
+    }
+
+    #[test]
+    fn test_bytes_n_sha256_of_file() {
+        let path = std::env::temp_dir().join(format!("soroban-cli-sha256-{}", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+        let t = ScSpecTypeDef::BytesN(ScSpecTypeBytesN { n: 32 });
+        let arg = format!("sha256:{}", path.display());
+        let val = from_string(&arg, &t, &[]).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let expected =
+            hex::decode("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+                .unwrap();
+        assert_eq!(
+            val,
+            ScVal::Object(Some(ScObject::Bytes(expected.try_into().unwrap())))
+        );
+        assert!(matches!(
+            from_string("sha256:/soroban-cli-missing", &t, &[]),
+            Err(StrValError::CannotReadFile { .. })
+        ));
+    }
+
+    #[test]
+    fn test_to_string_as() {
+        let bytes = |b: &[u8]| ScVal::Object(Some(ScObject::Bytes(b.to_vec().try_into().unwrap())));
+        assert_eq!(
+            to_string_as(&bytes(b"hello"), &"string".parse().unwrap()).unwrap(),
+            "hello"
+        );
+        assert_eq!(
+            to_string_as(&bytes(&[0xde, 0xad]), &"hex".parse().unwrap()).unwrap(),
+            "dead"
+        );
+        let items: ScVec = vec![bytes(b"a"), bytes(b"b")].try_into().unwrap();
+        let vec = ScVal::Object(Some(ScObject::Vec(items)));
+        let t: DecodeAs = "vec<string>".parse().unwrap();
+        assert_eq!(t.to_string(), "vec<string>");
+        assert_eq!(to_string_as(&vec, &t).unwrap(), r#"["a","b"]"#);
+        assert!(to_string_as(&ScVal::U32(1), &t).is_err());
+        assert!(matches!(
+            "vec<u8".parse::<DecodeAs>(),
+            Err(StrValError::UnknownDecodeType(_))
+        ));
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    fmt::Debug,
+    io::{self, Read},
+};
+
+use clap::Parser;
+use soroban_env_host::xdr::{Error as XdrError, ReadXdr, ScVal, TransactionEnvelope};
+
+use crate::{
+    rpc::{self, Client},
+    strval::{self, StrValError},
+};
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Signed transaction envelopes (base64 xdr, one per line) to send in order, `-` reads them
+    /// from stdin
+    #[clap(long = "envelope-xdr")]
+    envelope_xdr: String,
+    /// RPC server endpoint
+    #[clap(long)]
+    rpc_server_url: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("reading transaction envelopes from stdin: {0}")]
+    CannotReadEnvelopesFromStdin(io::Error),
+    #[error("parsing transaction envelope {envelope}: {error}")]
+    CannotParseEnvelope { envelope: String, error: XdrError },
+    #[error("parsing result: {0}")]
+    CannotParseResult(XdrError),
+    #[error("cannot print result {result:?}: {error}")]
+    CannotPrintResult { result: ScVal, error: StrValError },
+    #[error(transparent)]
+    Rpc(#[from] rpc::Error),
+}
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        let input = if self.envelope_xdr == "-" {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(Error::CannotReadEnvelopesFromStdin)?;
+            input
+        } else {
+            self.envelope_xdr.clone()
+        };
+        // Parse them all before sending any, so a typo doesn't leave the batch half sent
+        let envelopes = input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                TransactionEnvelope::from_xdr_base64(line.to_string()).map_err(|e| {
+                    Error::CannotParseEnvelope {
+                        envelope: line.to_string(),
+                        error: e,
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let client = Client::new(&self.rpc_server_url);
+        for envelope in &envelopes {
+            let submitted = client.send_transaction(envelope).await?;
+            submitted.report();
+            for result in &submitted.results {
+                let res =
+                    ScVal::from_xdr_base64(result.xdr.clone()).map_err(Error::CannotParseResult)?;
+                let res_str = strval::to_string(&res).map_err(|e| Error::CannotPrintResult {
+                    result: res,
+                    error: e,
+                })?;
+                println!("{}", res_str);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use std::fmt::Debug;
+
+use clap::Parser;
+
+use crate::utils::{self, ParseAmountError};
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Amount in display units (e.g. 1.5)
+    #[clap(long, allow_hyphen_values = true)]
+    amount: String,
+    /// Number of decimals of the token
+    #[clap(long, default_value = "7")]
+    decimals: u32,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    ParseAmount(#[from] ParseAmountError),
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        println!("{}", utils::parse_amount(&self.amount, self.decimals)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt::Debug;
+
+use clap::Parser;
+use serde_json::Value;
+use soroban_env_host::{
+    budget::Budget,
+    storage::Storage,
+    xdr::{
+        AccountId, Error as XdrError, HostFunction, InvokeHostFunctionOp, LedgerFootprint, Memo,
+        MuxedAccount, Operation, OperationBody, Preconditions, PublicKey, ReadXdr, ScObject, ScVal,
+        ScVec, SequenceNumber, Transaction, TransactionEnvelope, TransactionExt,
+        TransactionV1Envelope, Uint256, VecM,
+    },
+    Host, HostError,
+};
+use stellar_strkey::StrkeyPublicKeyEd25519;
+
+use crate::{
+    rpc::{self, Client},
+    snapshot,
+    strval::{self, StrValError},
+    utils,
+};
+
+use super::create::account_identifier;
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Contract ID of the token
+    #[clap(long = "id")]
+    contract_id: String,
+    /// Account (G...) to print the balance of
+    #[clap(long)]
+    of: StrkeyPublicKeyEd25519,
+    /// File to read the ledger state from (if using the sandbox)
+    #[clap(
+        long,
+        parse(from_os_str),
+        default_value = ".soroban/ledger.json",
+        conflicts_with = "rpc-server-url"
+    )]
+    ledger_file: std::path::PathBuf,
+    /// RPC server endpoint to simulate the balance call on
+    #[clap(long, conflicts_with = "ledger-file", requires = "source-account")]
+    rpc_server_url: Option<String>,
+    /// Funded account (G...) to build the simulated transaction for, which doesn't need to be the
+    /// --of account (nothing is signed)
+    #[clap(long, requires = "rpc-server-url")]
+    source_account: Option<StrkeyPublicKeyEd25519>,
+    /// Fee (in stroops) of the simulated transaction
+    #[clap(
+        long,
+        default_value = "100",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    fee: u32,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("cannot parse contract ID {contract_id}: {error}")]
+    CannotParseContractId {
+        contract_id: String,
+        error: utils::ParseContractIdError,
+    },
+    #[error("reading file {filepath}: {error}")]
+    CannotReadLedgerFile {
+        filepath: std::path::PathBuf,
+        error: snapshot::Error,
+    },
+    #[error("the rpc server returned no result for the balance call")]
+    MissingResult,
+    #[error("cannot print result {result:?}: {error}")]
+    CannotPrintResult { result: ScVal, error: StrValError },
+    #[error("host error: {}", utils::HostErrorDisplay(.0))]
+    Host(#[from] HostError),
+    #[error("xdr processing error: {0}")]
+    Xdr(#[from] XdrError),
+    #[error(transparent)]
+    Rpc(#[from] rpc::Error),
+}
+
+impl Cmd {
+    pub async fn run(&self) -> Result<(), Error> {
+        let contract_id = utils::contract_id_from_str(&self.contract_id).map_err(|e| {
+            Error::CannotParseContractId {
+                contract_id: self.contract_id.clone(),
+                error: e,
+            }
+        })?;
+        let account = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(self.of.0)));
+        let parameters: ScVec = vec![
+            ScVal::Object(Some(ScObject::Bytes(contract_id.try_into()?))),
+            ScVal::Symbol("balance".try_into()?),
+            account_identifier(&account)?,
+        ]
+        .try_into()?;
+
+        let res = if self.rpc_server_url.is_some() {
+            self.run_against_rpc_server(parameters).await?
+        } else {
+            self.run_in_sandbox(parameters)?
+        };
+        // Balances are big ints, which are rendered as JSON strings
+        let res_str = match strval::to_json(&res) {
+            Ok(Value::String(balance)) => balance,
+            Ok(balance) => balance.to_string(),
+            Err(e) => {
+                return Err(Error::CannotPrintResult {
+                    result: res,
+                    error: e,
+                })
+            }
+        };
+        println!("{}", res_str);
+        Ok(())
+    }
+
+    fn run_in_sandbox(&self, parameters: ScVec) -> Result<ScVal, Error> {
+        let state = snapshot::read(&self.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
+            filepath: self.ledger_file.clone(),
+            error: e,
+        })?;
+        let snap = snapshot::snap_from(&state);
+        let h = Host::with_storage_and_budget(
+            Storage::with_recording_footprint(snap),
+            Budget::default(),
+        );
+        h.set_ledger_info(state.info);
+        // Only reads, so there's nothing to commit
+        Ok(h.invoke_function(HostFunction::InvokeContract, parameters)?)
+    }
+
+    async fn run_against_rpc_server(&self, parameters: ScVec) -> Result<ScVal, Error> {
+        let client = Client::new(self.rpc_server_url.as_ref().unwrap());
+        // The transaction is only simulated, so it's left unsigned. It's built for the source
+        // account rather than --of, which may not exist on the network (its balance is then 0)
+        let source_account = self.source_account.as_ref().unwrap();
+        let sequence = client
+            .account_sequence(
+                &source_account.to_string(),
+                rpc::SequenceStrategy::Fetch,
+                None,
+            )
+            .await?;
+        let tx = Transaction {
+            source_account: MuxedAccount::Ed25519(Uint256(source_account.0)),
+            fee: self.fee,
+            seq_num: SequenceNumber(sequence + 1),
+            cond: Preconditions::None,
+            memo: Memo::None,
+            operations: vec![Operation {
+                source_account: None,
+                body: OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+                    function: HostFunction::InvokeContract,
+                    parameters,
+                    footprint: LedgerFootprint {
+                        read_only: VecM::default(),
+                        read_write: VecM::default(),
+                    },
+                }),
+            }]
+            .try_into()?,
+            ext: TransactionExt::V0,
+        };
+        let envelope = TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx,
+            signatures: VecM::default(),
+        });
+        let simulated = client.simulate_transaction(&envelope).await?;
+        let result = simulated.results.first().ok_or(Error::MissingResult)?;
+        Ok(ScVal::from_xdr_base64(result.xdr.clone())?)
+    }
+}
@@ -1,6 +1,9 @@
-use std::{array::TryFromSliceError, fmt::Debug, num::ParseIntError, rc::Rc};
+use std::{
+    array::TryFromSliceError, fmt::Debug, num::ParseIntError, sync::mpsc::RecvTimeoutError,
+    time::Duration,
+};
 
-use clap::Parser;
+use clap::{ArgGroup, Parser};
 use rand::Rng;
 use sha2::{Digest, Sha256};
 use soroban_env_host::{
@@ -10,19 +13,24 @@ use soroban_env_host::{
         AccountId, Error as XdrError, Hash, HashIdPreimage, HashIdPreimageSourceAccountContractId,
         HostFunction, InvokeHostFunctionOp, LedgerFootprint, LedgerKey::ContractData,
         LedgerKeyContractData, Memo, MuxedAccount, Operation, OperationBody, Preconditions,
-        PublicKey, ScHostStorageErrorCode, ScMap, ScMapEntry, ScObject,
-        ScStatic::LedgerKeyContractCode, ScStatus, ScVal, ScVec, SequenceNumber, Transaction,
-        TransactionEnvelope, TransactionExt, Uint256, VecM, WriteXdr,
+        PublicKey, ScBigInt, ScMap, ScMapEntry, ScObject, ScStatic::LedgerKeyContractCode, ScVal,
+        ScVec, SequenceNumber, Transaction, TransactionExt, Uint256, VecM, WriteXdr,
     },
     Host, HostError,
 };
 use stellar_strkey::StrkeyPublicKeyEd25519;
 
 use crate::{
-    rpc::{Client, Error as SorobanRpcError},
+    rpc::{self, Client, Error as SorobanRpcError},
     snapshot, utils,
 };
 
+/// Most decimal places a token can have, beyond which amounts stop fitting downstream tooling
+const MAX_DECIMAL: u32 = 18;
+
+/// Longest token name (in bytes) that fits the bytes object it's stored as
+const MAX_NAME_BYTES: usize = 256_000;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("reading file {filepath}: {error}")]
@@ -30,6 +38,11 @@ pub enum Error {
         filepath: std::path::PathBuf,
         error: snapshot::Error,
     },
+    #[error("writing contract ID to {filepath}: {error}")]
+    CannotWriteContractId {
+        filepath: std::path::PathBuf,
+        error: std::io::Error,
+    },
     #[error("committing file {filepath}: {error}")]
     CannotCommitLedgerFile {
         filepath: std::path::PathBuf,
@@ -37,27 +50,59 @@ pub enum Error {
     },
     #[error("cannot parse private key")]
     CannotParsePrivateKey,
+    #[error("writing unsigned transactions to {filepath}: {error}")]
+    CannotWriteUnsignedTransaction {
+        filepath: std::path::PathBuf,
+        error: utils::WriteTransactionError,
+    },
     #[error("cannot parse salt: {salt}")]
     CannotParseSalt { salt: String },
-    #[error(transparent)]
-    // TODO: the Display impl of host errors is pretty user-unfriendly
-    //       (it just calls Debug). I think we can do better than that
+    #[error("host error: {}", utils::HostErrorDisplay(.0))]
     Host(#[from] HostError),
+    #[error("cannot finish the host session, it is still referenced elsewhere")]
+    CannotFinishHost,
+    #[error("host created the token with contract ID {actual}, but {expected} was computed")]
+    ContractIdMismatch { expected: String, actual: String },
+    #[error("sandbox did not finish within the timeout ({0} s)")]
+    Timeout(u64),
+    #[error("sandbox thread panicked")]
+    SandboxThreadPanicked,
     #[error("invalid asset code: {asset}")]
     InvalidAssetCode { asset: String },
+    #[error(
+        "invalid token name: {length} bytes long, must be at most {}",
+        MAX_NAME_BYTES
+    )]
+    InvalidName { length: usize },
+    #[error("invalid decimal: {decimal}, must be at most {}", MAX_DECIMAL)]
+    InvalidDecimal { decimal: u32 },
+    #[error("invalid initial supply: {0}, must be positive")]
+    InvalidInitialSupply(i128),
     #[error("error parsing int: {0}")]
     ParseIntError(#[from] ParseIntError),
     #[error(transparent)]
     Client(#[from] SorobanRpcError),
+    #[error(
+        "token contract {contract_id} was created, but transaction {failed} of {total} failed, \
+        leaving it partially set up: {error}"
+    )]
+    PartiallyCreated {
+        contract_id: String,
+        failed: usize,
+        total: usize,
+        error: SorobanRpcError,
+    },
     #[error("internal conversion error: {0}")]
     TryFromSliceError(#[from] TryFromSliceError),
     #[error("xdr processing error: {0}")]
     Xdr(#[from] XdrError),
 }
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
+#[clap(group(ArgGroup::new("signer").args(&["private-strkey", "mnemonic", "source-account"])))]
 pub struct Cmd {
-    /// Administrator account for the token, will default to --private-strkey if not set
+    /// Administrator account for the token, defaults to the signing key (--private-strkey or
+    /// --mnemonic), or in the sandbox to the all-zeros account if there's none
     #[clap(long)]
     admin: Option<StrkeyPublicKeyEd25519>,
 
@@ -65,6 +110,16 @@ pub struct Cmd {
     #[clap(long, default_value = "7")]
     decimal: u32,
 
+    /// Amount (raw integer, see `token amount`) to mint right after creating the token
+    #[clap(long, value_name = "amount")]
+    initial_supply: Option<i128>,
+    /// Account to mint the initial supply to, defaults to the admin
+    #[clap(long, requires = "initial-supply")]
+    initial_supply_to: Option<StrkeyPublicKeyEd25519>,
+    /// Give up the admin role after minting the initial supply, so nobody can mint any more
+    #[clap(long, requires = "initial-supply")]
+    freeze_admin: bool,
+
     /// Long name of the token, e.g. "Stellar Lumens"
     #[clap(long)]
     name: String,
@@ -73,12 +128,16 @@ pub struct Cmd {
     #[clap(long)]
     symbol: String,
 
-    /// Custom salt 32-byte salt for the token id
+    /// Custom salt 32-byte salt for the token id, or `random` (alias `auto`) for a fresh one, which
+    /// the all-zeros default also gets
     #[clap(
         long,
         default_value = "0000000000000000000000000000000000000000000000000000000000000000"
     )]
     salt: String,
+    /// String to derive the salt from (its SHA-256 hash), for reproducible token ids
+    #[clap(long, conflicts_with = "salt")]
+    salt_seed: Option<String>,
 
     /// File to persist ledger state (if using the sandbox)
     #[clap(
@@ -88,36 +147,131 @@ pub struct Cmd {
         conflicts_with = "rpc-server-url"
     )]
     ledger_file: std::path::PathBuf,
+    /// Note to record in the ledger file alongside this change (for debugging)
+    #[clap(long, conflicts_with = "rpc-server-url")]
+    note: Option<String>,
+    /// File to write the contract ID to (in the format of the global --id-format), e.g. to
+    /// capture it in scripts
+    #[clap(long, parse(from_os_str))]
+    contract_id_out: Option<std::path::PathBuf>,
 
     /// RPC server endpoint
     #[clap(
         long,
         conflicts_with = "ledger-file",
-        requires = "private-strkey",
+        requires = "signer",
         requires = "network-passphrase"
     )]
     rpc_server_url: Option<String>,
     /// Private key to sign the transaction sent to the rpc server
     #[clap(long = "private-strkey", env)]
     private_strkey: Option<String>,
+    /// BIP-39 mnemonic to derive the key used to sign the transaction sent to the rpc server from
+    #[clap(long, env = "SOROBAN_MNEMONIC", hide_env_values = true)]
+    mnemonic: Option<String>,
+    /// Secret key of another signer of the source account to sign the transactions with, e.g.
+    /// for multisig admin accounts (can be repeated)
+    #[clap(
+        long = "additional-signer",
+        multiple = true,
+        requires = "rpc-server-url"
+    )]
+    additional_signers: Vec<String>,
+    /// Account to build the transactions for when they're signed elsewhere (see --unsigned-out)
+    #[clap(long, requires = "unsigned-out")]
+    source_account: Option<StrkeyPublicKeyEd25519>,
+    /// Write the unsigned transactions (base64 xdr, one per line) to this file instead of signing
+    /// and sending them, e.g. to sign them with a hardware wallet and send them with
+    /// `soroban submit`
+    #[clap(long, parse(from_os_str), requires = "rpc-server-url")]
+    unsigned_out: Option<std::path::PathBuf>,
+    /// SEP-0005 derivation path of the key to derive from the mnemonic
+    #[clap(long, default_value = utils::DEFAULT_HD_PATH)]
+    hd_path: String,
     /// Network passphrase to sign the transaction sent to the rpc server
     #[clap(long = "network-passphrase")]
     network_passphrase: Option<String>,
+    /// Fee (in stroops) to pay for the transaction sent to the rpc server
+    #[clap(
+        long,
+        default_value = "100",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    fee: u32,
+    /// How to get the sequence number of the source account (see the possible values)
+    #[clap(long, arg_enum, default_value = "fetch")]
+    sequence_strategy: rpc::SequenceStrategy,
+    /// Current sequence number of the source account, for --sequence-strategy provided
+    #[clap(long, required_if_eq("sequence-strategy", "provided"))]
+    sequence: Option<i64>,
+    /// Time limit (in seconds) for creating the token in the sandbox, or for each rpc request and
+    /// for the transactions to be applied when using an rpc server
+    #[clap(long, value_name = "seconds")]
+    timeout: Option<u64>,
+    /// Create and set up the token in a single transaction with one operation per step, so it
+    /// either fully succeeds or leaves nothing behind (--fee is then paid per operation)
+    #[clap(long, requires = "rpc-server-url")]
+    single_transaction: bool,
+    /// Times to resend each transaction when the rpc server can't be reached
+    #[clap(long, default_value_t = rpc::DEFAULT_SEND_RETRIES)]
+    retries: u32,
+
+    /// Print a summary of what the command will do to stderr before doing it
+    #[clap(long)]
+    explain: bool,
+    /// Print the summary of --explain and stop without creating the token
+    #[clap(long)]
+    dry_run: bool,
 }
 
 impl Cmd {
     pub async fn run(&self) -> Result<(), Error> {
-        // Hack: re-use contract_id_from_str to parse the 32-byte salt hex.
-        let salt: [u8; 32] =
+        if self.explain || self.dry_run {
+            eprintln!("{}", self.explanation());
+            if self.dry_run {
+                return Ok(());
+            }
+        }
+
+        let salt: [u8; 32] = if let Some(seed) = &self.salt_seed {
+            let salt: [u8; 32] = Sha256::digest(seed).into();
+            eprintln!("Salt: {}", hex::encode(salt));
+            salt
+        } else if self.salt == "random"
+            || self.salt == "auto"
+            // The all-zeros default gets a fresh salt too, so creating two tokens doesn't collide
+            || self.salt.trim_start_matches('0').is_empty()
+        {
+            let salt = rand::thread_rng().gen::<[u8; 32]>();
+            eprintln!("Salt: {}", hex::encode(salt));
+            salt
+        } else {
+            // Hack: re-use contract_id_from_str to parse the 32-byte salt hex.
             utils::contract_id_from_str(&self.salt).map_err(|_| Error::CannotParseSalt {
                 salt: self.salt.clone(),
-            })?;
+            })?
+        };
 
         if self.symbol.len() > 12 {
             return Err(Error::InvalidAssetCode {
                 asset: self.symbol.clone(),
             });
         }
+        if self.name.len() > MAX_NAME_BYTES {
+            return Err(Error::InvalidName {
+                length: self.name.len(),
+            });
+        }
+        if self.decimal > MAX_DECIMAL {
+            return Err(Error::InvalidDecimal {
+                decimal: self.decimal,
+            });
+        }
+        if let Some(supply) = self.initial_supply {
+            if supply <= 0 {
+                return Err(Error::InvalidInitialSupply(supply));
+            }
+        }
 
         let res_str = if self.rpc_server_url.is_some() {
             self.run_against_rpc_server(
@@ -128,13 +282,98 @@ impl Cmd {
                 self.decimal,
             )
             .await?
+        } else if let Some(timeout) = self.timeout {
+            self.run_in_sandbox_with_timeout(salt, timeout)?
         } else {
-            self.run_in_sandbox(salt, self.admin, &self.name, &self.symbol, self.decimal)?
+            self.run_in_sandbox(
+                salt,
+                self.admin,
+                &self.name,
+                &self.symbol,
+                self.decimal,
+                None,
+            )?
         };
         println!("{}", res_str);
+        if let Some(f) = &self.contract_id_out {
+            utils::write_contract_id(f, &res_str).map_err(|e| Error::CannotWriteContractId {
+                filepath: f.clone(),
+                error: e,
+            })?;
+        }
         Ok(())
     }
 
+    fn explanation(&self) -> String {
+        let admin = if let Some(admin) = &self.admin {
+            admin.to_string()
+        } else if let Some(account) = &self.source_account {
+            account.to_string()
+        } else if let Ok(key) = utils::signing_key(
+            self.private_strkey.as_deref(),
+            self.mnemonic.as_deref(),
+            &self.hd_path,
+        ) {
+            StrkeyPublicKeyEd25519(key.public.to_bytes()).to_string()
+        } else if self.rpc_server_url.is_some() {
+            "the signing account".to_string()
+        } else {
+            "the all-zeros account".to_string()
+        };
+        let token = format!(
+            "Will create token '{}' ({}) with {} decimals, admin {}",
+            self.symbol, self.name, self.decimal, admin
+        );
+        let token = match (self.initial_supply, &self.initial_supply_to) {
+            (Some(supply), Some(to)) => format!("{}, minting {} to {}", token, supply, to),
+            (Some(supply), None) => format!("{}, minting {} to the admin", token, supply),
+            (None, _) => token,
+        };
+        let token = if self.freeze_admin {
+            format!("{}, then giving up the admin role", token)
+        } else {
+            token
+        };
+        if let Some(rpc_server_url) = &self.rpc_server_url {
+            format!(
+                "{}, on network '{}' via {}, in {} transactions",
+                token,
+                self.network_passphrase.as_deref().unwrap_or_default(),
+                rpc_server_url,
+                if self.single_transaction {
+                    1
+                } else {
+                    2 + usize::from(self.initial_supply.is_some()) + usize::from(self.freeze_admin)
+                }
+            )
+        } else {
+            format!(
+                "{}, in the ledger file {}",
+                token,
+                self.ledger_file.display()
+            )
+        }
+    }
+
+    fn run_in_sandbox_with_timeout(&self, salt: [u8; 32], timeout: u64) -> Result<String, Error> {
+        let cmd = self.clone();
+        let deadline = utils::Deadline::default();
+        match utils::run_with_timeout(Duration::from_secs(timeout), &deadline, move |deadline| {
+            cmd.run_in_sandbox(
+                salt,
+                cmd.admin,
+                &cmd.name,
+                &cmd.symbol,
+                cmd.decimal,
+                Some(deadline),
+            )
+        }) {
+            Ok(res) => res,
+            Err(RecvTimeoutError::Timeout) => Err(Error::Timeout(timeout)),
+            Err(RecvTimeoutError::Disconnected) => Err(Error::SandboxThreadPanicked),
+        }
+    }
+
     fn run_in_sandbox(
         &self,
         salt: [u8; 32],
@@ -142,18 +381,27 @@ impl Cmd {
         name: &str,
         symbol: &str,
         decimal: u32,
+        deadline: Option<&utils::Deadline>,
     ) -> Result<String, Error> {
-        // Use 0s as default admin key
-        let admin = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
-            admin_param
-                .unwrap_or_else(|| {
-                    StrkeyPublicKeyEd25519::from_string(
-                        "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF",
-                    )
-                    .unwrap()
-                })
-                .0,
-        )));
+        // Default to the signing key like the rpc path, and to 0s only when there's none
+        let admin_key = match admin_param {
+            Some(admin) => admin.0,
+            None => match utils::signing_key(
+                self.private_strkey.as_deref(),
+                self.mnemonic.as_deref(),
+                &self.hd_path,
+            ) {
+                Ok(key) => key.public.to_bytes(),
+                Err(utils::ParsePrivateKeyError::MissingSigningKey) => {
+                    eprintln!(
+                        "No --admin or signing key given, using the all-zeros account as admin"
+                    );
+                    [0; 32]
+                }
+                Err(_) => return Err(Error::CannotParsePrivateKey),
+            },
+        };
+        let admin = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(admin_key)));
 
         // Initialize storage and host
         // TODO: allow option to separate input and output file
@@ -162,9 +410,7 @@ impl Cmd {
             error: e,
         })?;
 
-        let snap = Rc::new(snapshot::Snap {
-            ledger_entries: state.1.clone(),
-        });
+        let snap = snapshot::snap_from(&state);
         let h = Host::with_storage_and_budget(
             Storage::with_recording_footprint(snap),
             Budget::default(),
@@ -172,7 +418,7 @@ impl Cmd {
 
         h.set_source_account(admin.clone());
 
-        let mut ledger_info = state.0.clone();
+        let mut ledger_info = state.info.clone();
         ledger_info.sequence_number += 1;
         ledger_info.timestamp += 5;
         h.set_ledger_info(ledger_info.clone());
@@ -183,23 +429,41 @@ impl Cmd {
         )?;
         let res_str = utils::vec_to_hash(&res)?;
 
+        // The rpc path can only compute the id, so make sure the computation agrees with the host
         let contract_id = get_contract_id(salt, admin.clone())?;
+        let expected = utils::contract_id_to_string(&contract_id);
+        if res_str != expected {
+            return Err(Error::ContractIdMismatch {
+                expected,
+                actual: res_str,
+            });
+        }
         h.invoke_function(
             HostFunction::InvokeContract,
-            init_parameters(contract_id, &admin, name, symbol, decimal),
+            init_parameters(contract_id, &admin, name, symbol, decimal)?,
         )?;
+        for (parameters, _) in self.supply_invocations(contract_id, &admin)? {
+            h.invoke_function(HostFunction::InvokeContract, parameters)?;
+        }
 
-        let (storage, _, _) = h.try_finish().map_err(|_h| {
-            HostError::from(ScStatus::HostStorageError(
-                ScHostStorageErrorCode::UnknownError,
-            ))
-        })?;
+        let (storage, _, _) = h.try_finish().map_err(|_h| Error::CannotFinishHost)?;
 
-        snapshot::commit(state.1, ledger_info, &storage.map, &self.ledger_file).map_err(|e| {
-            Error::CannotCommitLedgerFile {
-                filepath: self.ledger_file.clone(),
-                error: e,
+        if let Some(deadline) = deadline {
+            if !deadline.begin_commit() {
+                return Err(Error::Timeout(self.timeout.unwrap_or_default()));
             }
+        }
+        snapshot::commit(
+            state.entries,
+            ledger_info,
+            state.notes,
+            self.note.as_deref(),
+            &storage.map,
+            &self.ledger_file,
+        )
+        .map_err(|e| Error::CannotCommitLedgerFile {
+            filepath: self.ledger_file.clone(),
+            error: e,
         })?;
         Ok(res_str)
     }
@@ -212,53 +476,161 @@ impl Cmd {
         symbol: &str,
         decimal: u32,
     ) -> Result<String, Error> {
-        let client = Client::new(self.rpc_server_url.as_ref().unwrap());
-        let key = utils::parse_private_key(self.private_strkey.as_ref().unwrap())
-            .map_err(|_| Error::CannotParsePrivateKey)?;
-        let salt_val = if salt == [0; 32] {
-            rand::thread_rng().gen::<[u8; 32]>()
+        let client = Client::new(self.rpc_server_url.as_ref().unwrap())
+            .with_timeout(self.timeout.map(Duration::from_secs))
+            .with_retries(self.retries);
+        // The key is only missing when the transactions are signed elsewhere
+        let (source_account, key) = if let Some(account) = &self.source_account {
+            (account.0, None)
         } else {
-            salt
+            let key = utils::signing_key(
+                self.private_strkey.as_deref(),
+                self.mnemonic.as_deref(),
+                &self.hd_path,
+            )
+            .map_err(|_| Error::CannotParsePrivateKey)?;
+            (key.public.to_bytes(), Some(key))
         };
-
         let admin_key = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
-            admin.unwrap_or_else(|| key.public.to_bytes()),
+            admin.unwrap_or(source_account),
         )));
 
         // Get the account sequence number
-        let public_strkey =
-            stellar_strkey::StrkeyPublicKeyEd25519(key.public.to_bytes()).to_string();
-        // TODO: use symbols for the method names (both here and in serve)
-        let account_details = client.get_account(&public_strkey).await?;
-        // TODO: create a cmdline parameter for the fee instead of simply using the minimum fee
-        let fee: u32 = 100;
-        let sequence = account_details.sequence.parse::<i64>()?;
-        let contract_id = get_contract_id(salt_val, admin_key.clone())?;
-
-        client
-            .send_transaction(&build_tx(
-                build_create_token_op(&Hash(contract_id), salt_val)?,
-                sequence + 1,
-                fee,
-                self.network_passphrase.as_ref().unwrap(),
-                &key,
-            )?)
+        let public_strkey = StrkeyPublicKeyEd25519(source_account).to_string();
+        eprintln!("Source account: {}", public_strkey);
+        let sequence = client
+            .account_sequence(&public_strkey, self.sequence_strategy, self.sequence)
             .await?;
+        let fee = self.fee;
+        let contract_id = get_contract_id(salt, admin_key.clone())?;
+
+        let mut ops = vec![
+            build_create_token_op(&Hash(contract_id), salt)?,
+            build_init_op(
+                &Hash(contract_id),
+                init_parameters(contract_id, &admin_key, name, symbol, decimal)?,
+            )?,
+        ];
+        for (parameters, footprint) in self.supply_invocations(contract_id, &admin_key)? {
+            ops.push(build_invoke_op(parameters, footprint));
+        }
+        let txs = build_txs(
+            ops,
+            sequence + 1,
+            fee,
+            source_account,
+            self.single_transaction,
+        )?;
+        let last_sequence = sequence + i64::try_from(txs.len()).unwrap();
+        if let Some(path) = &self.unsigned_out {
+            utils::write_unsigned_transactions(path, &txs).map_err(|e| {
+                Error::CannotWriteUnsignedTransaction {
+                    filepath: path.clone(),
+                    error: e,
+                }
+            })?;
+            eprintln!("Unsigned transactions written to {}", path.display());
+            return Ok(utils::contract_id_to_string(&contract_id));
+        }
 
-        client
-            .send_transaction(&build_tx(
-                build_init_op(
-                    &Hash(contract_id),
-                    init_parameters(contract_id, &admin_key, name, symbol, decimal),
-                )?,
-                sequence + 2,
-                fee,
-                self.network_passphrase.as_ref().unwrap(),
-                &key,
-            )?)
-            .await?;
+        let key = key.expect("--source-account requires --unsigned-out");
+        let additional_keys = self
+            .additional_signers
+            .iter()
+            .map(|secret| utils::parse_private_key(secret))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::CannotParsePrivateKey)?;
+        let network_passphrase = self.network_passphrase.as_ref().unwrap();
+        for (i, tx) in txs.iter().enumerate() {
+            let mut envelope = utils::sign_transaction(&key, tx, network_passphrase)?;
+            for additional_key in &additional_keys {
+                envelope = utils::add_signature(envelope, additional_key, network_passphrase)?;
+            }
+            match client.send_transaction(&envelope).await {
+                Ok(submitted) => submitted.report(),
+                // Once the contract exists, a failure leaves it half set up rather than undone
+                Err(e) if i > 0 => {
+                    return Err(Error::PartiallyCreated {
+                        contract_id: utils::contract_id_to_string(&contract_id),
+                        failed: i + 1,
+                        total: txs.len(),
+                        error: e,
+                    })
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        rpc::record_sequence(self.sequence_strategy, &public_strkey, last_sequence)?;
 
-        Ok(hex::encode(&contract_id))
+        Ok(utils::contract_id_to_string(&contract_id))
+    }
+
+    /// Parameters and footprints of the invocations that follow `init`: minting the initial
+    /// supply, and giving up the admin role, if asked to
+    fn supply_invocations(
+        &self,
+        contract_id: [u8; 32],
+        admin: &AccountId,
+    ) -> Result<Vec<(ScVec, LedgerFootprint)>, Error> {
+        let supply = match self.initial_supply {
+            Some(supply) => supply,
+            None => return Ok(Vec::new()),
+        };
+        let to = match &self.initial_supply_to {
+            Some(to) => AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(to.0))),
+            None => admin.clone(),
+        };
+        let data_key = |key: ScVal| {
+            ContractData(LedgerKeyContractData {
+                contract_id: Hash(contract_id),
+                key,
+            })
+        };
+        let admin_key = data_key(ScVal::Symbol("Admin".try_into()?));
+
+        let mut invocations = vec![(
+            vec![
+                ScVal::Object(Some(ScObject::Bytes(contract_id.try_into()?))),
+                ScVal::Symbol("mint".try_into()?),
+                invoker_signature()?,
+                // Invoker signatures don't use nonces, so it must be zero
+                ScVal::Object(Some(ScObject::BigInt(ScBigInt::Zero))),
+                account_identifier(&to)?,
+                positive_big_int(supply)?,
+            ]
+            .try_into()?,
+            LedgerFootprint {
+                read_only: vec![
+                    admin_key.clone(),
+                    data_key(tagged("State", vec![account_identifier(&to)?])?),
+                ]
+                .try_into()?,
+                read_write: vec![data_key(tagged("Balance", vec![account_identifier(&to)?])?)]
+                    .try_into()?,
+            },
+        )];
+        if self.freeze_admin {
+            // No one has the secret key of the all-zeros ed25519 key, so no one can act as admin
+            let nobody = tagged(
+                "Ed25519",
+                vec![ScVal::Object(Some(ScObject::Bytes([0u8; 32].try_into()?)))],
+            )?;
+            invocations.push((
+                vec![
+                    ScVal::Object(Some(ScObject::Bytes(contract_id.try_into()?))),
+                    ScVal::Symbol("set_admin".try_into()?),
+                    invoker_signature()?,
+                    ScVal::Object(Some(ScObject::BigInt(ScBigInt::Zero))),
+                    nobody,
+                ]
+                .try_into()?,
+                LedgerFootprint {
+                    read_only: VecM::default(),
+                    read_write: vec![admin_key].try_into()?,
+                },
+            ));
+        }
+        Ok(invocations)
     }
 }
 
@@ -273,23 +645,51 @@ fn get_contract_id(salt: [u8; 32], source_account: AccountId) -> Result<[u8; 32]
 }
 
 fn build_tx(
-    op: Operation,
+    ops: Vec<Operation>,
     sequence: i64,
     fee: u32,
-    network_passphrase: &str,
-    key: &ed25519_dalek::Keypair,
-) -> Result<TransactionEnvelope, Error> {
-    let tx = Transaction {
-        source_account: MuxedAccount::Ed25519(Uint256(key.public.to_bytes())),
+    source_account: [u8; 32],
+) -> Result<Transaction, Error> {
+    Ok(Transaction {
+        source_account: MuxedAccount::Ed25519(Uint256(source_account)),
         fee,
         seq_num: SequenceNumber(sequence),
         cond: Preconditions::None,
         memo: Memo::None,
-        operations: vec![op].try_into()?,
+        operations: ops.try_into()?,
         ext: TransactionExt::V0,
-    };
+    })
+}
 
-    Ok(utils::sign_transaction(key, &tx, network_passphrase)?)
+// Builds either one transaction with all the operations, or one transaction per operation with
+// consecutive sequence numbers from `sequence`
+fn build_txs(
+    ops: Vec<Operation>,
+    sequence: i64,
+    fee: u32,
+    source_account: [u8; 32],
+    single_transaction: bool,
+) -> Result<Vec<Transaction>, Error> {
+    if single_transaction {
+        // Fees are charged per operation
+        let op_count = u32::try_from(ops.len()).unwrap();
+        return Ok(vec![build_tx(
+            ops,
+            sequence,
+            fee.saturating_mul(op_count),
+            source_account,
+        )?]);
+    }
+    let mut txs = Vec::new();
+    for op in ops {
+        txs.push(build_tx(
+            vec![op],
+            sequence + i64::try_from(txs.len()).unwrap(),
+            fee,
+            source_account,
+        )?);
+    }
+    Ok(txs)
 }
 
 fn build_create_token_op(contract_id: &Hash, salt: [u8; 32]) -> Result<Operation, Error> {
@@ -320,42 +720,79 @@ fn init_parameters(
     name: &str,
     symbol: &str,
     decimals: u32,
-) -> ScVec {
-    vec![
+) -> Result<ScVec, Error> {
+    Ok(vec![
         // Contract ID
-        ScVal::Object(Some(ScObject::Bytes(contract_id.try_into().unwrap()))),
+        ScVal::Object(Some(ScObject::Bytes(contract_id.try_into()?))),
         // Method
-        ScVal::Symbol("init".try_into().unwrap()),
+        ScVal::Symbol("init".try_into()?),
         // Admin Identifier
-        ScVal::Object(Some(ScObject::Vec(
-            vec![
-                ScVal::Symbol("Account".try_into().unwrap()),
-                ScVal::Object(Some(ScObject::AccountId(admin.clone()))),
-            ]
-            .try_into()
-            .unwrap(),
-        ))),
+        account_identifier(admin)?,
         // TokenMetadata
-        ScVal::Object(Some(ScObject::Map(
-            ScMap::sorted_from(vec![
-                ScMapEntry {
-                    key: ScVal::Symbol("decimals".try_into().unwrap()),
-                    val: ScVal::U32(decimals),
-                },
-                ScMapEntry {
-                    key: ScVal::Symbol("name".try_into().unwrap()),
-                    val: ScVal::Object(Some(ScObject::Bytes(name.try_into().unwrap()))),
-                },
-                ScMapEntry {
-                    key: ScVal::Symbol("symbol".try_into().unwrap()),
-                    val: ScVal::Object(Some(ScObject::Bytes(symbol.try_into().unwrap()))),
-                },
-            ])
-            .unwrap(),
-        ))),
+        ScVal::Object(Some(ScObject::Map(ScMap::sorted_from(vec![
+            ScMapEntry {
+                key: ScVal::Symbol("decimals".try_into()?),
+                val: ScVal::U32(decimals),
+            },
+            ScMapEntry {
+                key: ScVal::Symbol("name".try_into()?),
+                val: ScVal::Object(Some(ScObject::Bytes(
+                    name.try_into()
+                        .map_err(|_| Error::InvalidName { length: name.len() })?,
+                ))),
+            },
+            ScMapEntry {
+                key: ScVal::Symbol("symbol".try_into()?),
+                val: ScVal::Object(Some(ScObject::Bytes(symbol.try_into().map_err(|_| {
+                    Error::InvalidAssetCode {
+                        asset: symbol.to_string(),
+                    }
+                })?))),
+            },
+        ])?))),
     ]
-    .try_into()
-    .unwrap()
+    .try_into()?)
+}
+
+// Enum values of the token contract are vecs of the case name followed by its fields
+fn tagged(case: &str, fields: Vec<ScVal>) -> Result<ScVal, XdrError> {
+    let mut items = vec![ScVal::Symbol(case.try_into()?)];
+    items.extend(fields);
+    Ok(ScVal::Object(Some(ScObject::Vec(items.try_into()?))))
+}
+
+/// Token contract `Identifier` of an account, e.g. to pass as the admin or balance holder
+pub fn account_identifier(account: &AccountId) -> Result<ScVal, XdrError> {
+    tagged(
+        "Account",
+        vec![ScVal::Object(Some(ScObject::AccountId(account.clone())))],
+    )
+}
+
+fn invoker_signature() -> Result<ScVal, XdrError> {
+    tagged("Invoker", Vec::new())
+}
+
+fn positive_big_int(amount: i128) -> Result<ScVal, Error> {
+    let bytes: Vec<u8> = amount
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|b| *b == 0)
+        .collect();
+    Ok(ScVal::Object(Some(ScObject::BigInt(ScBigInt::Positive(
+        bytes.try_into()?,
+    )))))
+}
+
+fn build_invoke_op(parameters: ScVec, footprint: LedgerFootprint) -> Operation {
+    Operation {
+        source_account: None,
+        body: OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+            function: HostFunction::InvokeContract,
+            parameters,
+            footprint,
+        }),
+    }
 }
 
 fn build_init_op(contract_id: &Hash, parameters: ScVec) -> Result<Operation, Error> {
@@ -392,15 +829,65 @@ mod tests {
         let salt = [0u8; 32];
         let op = build_create_token_op(&contract_id, salt);
         assert!(op.is_ok());
-        let result = build_tx(
-            op.unwrap(),
-            300,
-            1,
-            "Public Global Stellar Network ; September 2015",
-            &utils::parse_private_key("SBFGFF27Y64ZUGFAIG5AMJGQODZZKV2YQKAVUUN4HNE24XZXD2OEUVUP")
-                .unwrap(),
-        );
+        let key =
+            utils::parse_private_key("SBFGFF27Y64ZUGFAIG5AMJGQODZZKV2YQKAVUUN4HNE24XZXD2OEUVUP")
+                .unwrap();
+        let result = build_tx(vec![op.unwrap()], 300, 1, key.public.to_bytes());
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_build_txs() {
+        let contract_id = Hash([0u8; 32]);
+        let ops = || {
+            vec![
+                build_create_token_op(&contract_id, [0u8; 32]).unwrap(),
+                build_init_op(&contract_id, ScVec::default()).unwrap(),
+                build_invoke_op(
+                    ScVec::default(),
+                    LedgerFootprint {
+                        read_only: VecM::default(),
+                        read_write: VecM::default(),
+                    },
+                ),
+            ]
+        };
+
+        let txs = build_txs(ops(), 5, 100, [1; 32], true).unwrap();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].operations.len(), 3);
+        assert_eq!(txs[0].fee, 300);
+        assert_eq!(txs[0].seq_num, SequenceNumber(5));
+
+        let txs = build_txs(ops(), 5, 100, [1; 32], false).unwrap();
+        assert_eq!(txs.len(), 3);
+        for (tx, sequence) in txs.iter().zip(5..) {
+            assert_eq!(tx.operations.len(), 1);
+            assert_eq!(tx.fee, 100);
+            assert_eq!(tx.seq_num, SequenceNumber(sequence));
+        }
+    }
+
+    #[test]
+    fn test_init_parameters() {
+        let admin = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([0; 32])));
+        assert!(init_parameters([0; 32], &admin, "Token", "TKN", 7).is_ok());
+        let name = "a".repeat(MAX_NAME_BYTES + 1);
+        assert!(matches!(
+            init_parameters([0; 32], &admin, &name, "TKN", 7),
+            Err(Error::InvalidName { length }) if length == name.len()
+        ));
+    }
+
+    #[test]
+    fn test_positive_big_int() {
+        let big_int = |bytes: Vec<u8>| {
+            ScVal::Object(Some(ScObject::BigInt(ScBigInt::Positive(
+                bytes.try_into().unwrap(),
+            ))))
+        };
+        assert_eq!(positive_big_int(1).unwrap(), big_int(vec![1]));
+        assert_eq!(positive_big_int(0x1_0000).unwrap(), big_int(vec![1, 0, 0]));
+    }
 }
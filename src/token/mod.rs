@@ -2,6 +2,8 @@ use std::fmt::Debug;
 
 use clap::{Parser, Subcommand};
 
+pub mod amount;
+pub mod balance;
 pub mod create;
 pub mod wrap;
 
@@ -17,6 +19,10 @@ enum Cmd {
     Create(create::Cmd),
     /// Deploy a token contract to wrap an existing Stellar classic asset for smart contract usage
     Wrap(wrap::Cmd),
+    /// Print the balance of an account in a token contract
+    Balance(balance::Cmd),
+    /// Convert an amount in display units to the raw integer amount, given the token decimals
+    Amount(amount::Cmd),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -25,6 +31,10 @@ pub enum Error {
     Create(#[from] create::Error),
     #[error(transparent)]
     Wrap(#[from] wrap::Error),
+    #[error(transparent)]
+    Balance(#[from] balance::Error),
+    #[error(transparent)]
+    Amount(#[from] amount::Error),
 }
 
 impl Root {
@@ -32,6 +42,8 @@ impl Root {
         match &self.cmd {
             Cmd::Create(create) => create.run().await?,
             Cmd::Wrap(wrap) => wrap.run().await?,
+            Cmd::Balance(balance) => balance.run().await?,
+            Cmd::Amount(amount) => amount.run()?,
         }
         Ok(())
     }
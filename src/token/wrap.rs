@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{ArgGroup, Parser};
 use regex::Regex;
 use sha2::{Digest, Sha256};
 use soroban_env_host::{
@@ -8,17 +8,19 @@ use soroban_env_host::{
         AccountId, AlphaNum12, AlphaNum4, Asset, AssetCode12, AssetCode4, Error as XdrError, Hash,
         HashIdPreimage, HostFunction, InvokeHostFunctionOp, LedgerFootprint,
         LedgerKey::ContractData, LedgerKeyContractData, Memo, MuxedAccount, Operation,
-        OperationBody, Preconditions, PublicKey, ScHostStorageErrorCode, ScObject,
-        ScStatic::LedgerKeyContractCode, ScStatus, ScVal, SequenceNumber, Transaction,
-        TransactionEnvelope, TransactionExt, Uint256, VecM, WriteXdr,
+        OperationBody, Preconditions, PublicKey, ScObject, ScStatic::LedgerKeyContractCode, ScVal,
+        SequenceNumber, Transaction, TransactionEnvelope, TransactionExt, Uint256, VecM, WriteXdr,
     },
     Host, HostError,
 };
-use std::{array::TryFromSliceError, fmt::Debug, num::ParseIntError, rc::Rc};
+use std::{
+    array::TryFromSliceError, fmt::Debug, num::ParseIntError, sync::mpsc::RecvTimeoutError,
+    time::Duration,
+};
 use stellar_strkey::StrkeyPublicKeyEd25519;
 
 use crate::{
-    rpc::{Client, Error as SorobanRpcError},
+    rpc::{self, Client, Error as SorobanRpcError},
     snapshot, utils,
 };
 
@@ -35,15 +37,24 @@ pub enum Error {
         filepath: std::path::PathBuf,
         error: snapshot::Error,
     },
+    #[error("writing contract ID to {filepath}: {error}")]
+    CannotWriteContractId {
+        filepath: std::path::PathBuf,
+        error: std::io::Error,
+    },
     #[error("committing file {filepath}: {error}")]
     CannotCommitLedgerFile {
         filepath: std::path::PathBuf,
         error: snapshot::Error,
     },
-    #[error(transparent)]
-    // TODO: the Display impl of host errors is pretty user-unfriendly
-    //       (it just calls Debug). I think we can do better than that
+    #[error("host error: {}", utils::HostErrorDisplay(.0))]
     Host(#[from] HostError),
+    #[error("cannot finish the host session, it is still referenced elsewhere")]
+    CannotFinishHost,
+    #[error("sandbox did not finish within the timeout ({0} s)")]
+    Timeout(u64),
+    #[error("sandbox thread panicked")]
+    SandboxThreadPanicked,
     #[error("invalid asset code: {asset}")]
     InvalidAssetCode { asset: String },
     #[error("error parsing int: {0}")]
@@ -56,7 +67,8 @@ pub enum Error {
     Xdr(#[from] XdrError),
 }
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
+#[clap(group(ArgGroup::new("signer").args(&["private-strkey", "mnemonic"])))]
 pub struct Cmd {
     /// ID of the Stellar classic asset to wrap, e.g. "USDC:G...5"
     #[clap(long)]
@@ -70,21 +82,51 @@ pub struct Cmd {
         conflicts_with = "rpc-server-url"
     )]
     ledger_file: std::path::PathBuf,
+    /// Note to record in the ledger file alongside this change (for debugging)
+    #[clap(long, conflicts_with = "rpc-server-url")]
+    note: Option<String>,
+    /// File to write the contract ID to (in the format of the global --id-format), e.g. to
+    /// capture it in scripts
+    #[clap(long, parse(from_os_str))]
+    contract_id_out: Option<std::path::PathBuf>,
 
     /// RPC server endpoint
     #[clap(
         long,
         conflicts_with = "ledger-file",
-        requires = "private-strkey",
+        requires = "signer",
         requires = "network-passphrase"
     )]
     rpc_server_url: Option<String>,
     /// Private key to sign the transaction sent to the rpc server
     #[clap(long = "private-strkey", env)]
     private_strkey: Option<String>,
+    /// BIP-39 mnemonic to derive the key used to sign the transaction sent to the rpc server from
+    #[clap(long, env = "SOROBAN_MNEMONIC", hide_env_values = true)]
+    mnemonic: Option<String>,
+    /// SEP-0005 derivation path of the key to derive from the mnemonic
+    #[clap(long, default_value = utils::DEFAULT_HD_PATH)]
+    hd_path: String,
     /// Network passphrase to sign the transaction sent to the rpc server
     #[clap(long = "network-passphrase")]
     network_passphrase: Option<String>,
+    /// Fee (in stroops) to pay for the transaction sent to the rpc server
+    #[clap(
+        long,
+        default_value = "100",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    fee: u32,
+    /// How to get the sequence number of the source account (see the possible values)
+    #[clap(long, arg_enum, default_value = "fetch")]
+    sequence_strategy: rpc::SequenceStrategy,
+    /// Current sequence number of the source account, for --sequence-strategy provided
+    #[clap(long, required_if_eq("sequence-strategy", "provided"))]
+    sequence: Option<i64>,
+    /// Time limit (in seconds) for wrapping the asset in the sandbox, or for each rpc request and
+    /// for the transaction to be applied when using an rpc server
+    #[clap(long, value_name = "seconds")]
+    timeout: Option<u64>,
 }
 
 impl Cmd {
@@ -94,14 +136,38 @@ impl Cmd {
 
         let res_str = if self.rpc_server_url.is_some() {
             self.run_against_rpc_server(asset).await?
+        } else if let Some(timeout) = self.timeout {
+            self.run_in_sandbox_with_timeout(asset, timeout)?
         } else {
-            self.run_in_sandbox(&asset)?
+            self.run_in_sandbox(&asset, None)?
         };
         println!("{}", res_str);
+        if let Some(f) = &self.contract_id_out {
+            utils::write_contract_id(f, &res_str).map_err(|e| Error::CannotWriteContractId {
+                filepath: f.clone(),
+                error: e,
+            })?;
+        }
         Ok(())
     }
 
-    fn run_in_sandbox(&self, asset: &Asset) -> Result<String, Error> {
+    fn run_in_sandbox_with_timeout(&self, asset: Asset, timeout: u64) -> Result<String, Error> {
+        let cmd = self.clone();
+        let deadline = utils::Deadline::default();
+        match utils::run_with_timeout(Duration::from_secs(timeout), &deadline, move |deadline| {
+            cmd.run_in_sandbox(&asset, Some(deadline))
+        }) {
+            Ok(res) => res,
+            Err(RecvTimeoutError::Timeout) => Err(Error::Timeout(timeout)),
+            Err(RecvTimeoutError::Disconnected) => Err(Error::SandboxThreadPanicked),
+        }
+    }
+
+    fn run_in_sandbox(
+        &self,
+        asset: &Asset,
+        deadline: Option<&utils::Deadline>,
+    ) -> Result<String, Error> {
         // Initialize storage and host
         // TODO: allow option to separate input and output file
         let state = snapshot::read(&self.ledger_file).map_err(|e| Error::CannotReadLedgerFile {
@@ -109,15 +175,13 @@ impl Cmd {
             error: e,
         })?;
 
-        let snap = Rc::new(snapshot::Snap {
-            ledger_entries: state.1.clone(),
-        });
+        let snap = snapshot::snap_from(&state);
         let h = Host::with_storage_and_budget(
             Storage::with_recording_footprint(snap),
             Budget::default(),
         );
 
-        let mut ledger_info = state.0.clone();
+        let mut ledger_info = state.info.clone();
         ledger_info.sequence_number += 1;
         ledger_info.timestamp += 5;
         h.set_ledger_info(ledger_info.clone());
@@ -133,34 +197,46 @@ impl Cmd {
         )?;
         let res_str = utils::vec_to_hash(&res)?;
 
-        let (storage, _, _) = h.try_finish().map_err(|_h| {
-            HostError::from(ScStatus::HostStorageError(
-                ScHostStorageErrorCode::UnknownError,
-            ))
-        })?;
+        let (storage, _, _) = h.try_finish().map_err(|_h| Error::CannotFinishHost)?;
 
-        snapshot::commit(state.1, ledger_info, &storage.map, &self.ledger_file).map_err(|e| {
-            Error::CannotCommitLedgerFile {
-                filepath: self.ledger_file.clone(),
-                error: e,
+        if let Some(deadline) = deadline {
+            if !deadline.begin_commit() {
+                return Err(Error::Timeout(self.timeout.unwrap_or_default()));
             }
+        }
+        snapshot::commit(
+            state.entries,
+            ledger_info,
+            state.notes,
+            self.note.as_deref(),
+            &storage.map,
+            &self.ledger_file,
+        )
+        .map_err(|e| Error::CannotCommitLedgerFile {
+            filepath: self.ledger_file.clone(),
+            error: e,
         })?;
         Ok(res_str)
     }
 
     async fn run_against_rpc_server(&self, asset: Asset) -> Result<String, Error> {
-        let client = Client::new(self.rpc_server_url.as_ref().unwrap());
-        let key = utils::parse_private_key(self.private_strkey.as_ref().unwrap())
-            .map_err(|_| Error::CannotParsePrivateKey)?;
+        let client = Client::new(self.rpc_server_url.as_ref().unwrap())
+            .with_timeout(self.timeout.map(Duration::from_secs));
+        let key = utils::signing_key(
+            self.private_strkey.as_deref(),
+            self.mnemonic.as_deref(),
+            &self.hd_path,
+        )
+        .map_err(|_| Error::CannotParsePrivateKey)?;
 
         // Get the account sequence number
         let public_strkey =
             stellar_strkey::StrkeyPublicKeyEd25519(key.public.to_bytes()).to_string();
-        // TODO: use symbols for the method names (both here and in serve)
-        let account_details = client.get_account(&public_strkey).await?;
-        // TODO: create a cmdline parameter for the fee instead of simply using the minimum fee
-        let fee: u32 = 100;
-        let sequence = account_details.sequence.parse::<i64>()?;
+        eprintln!("Source account: {}", public_strkey);
+        let sequence = client
+            .account_sequence(&public_strkey, self.sequence_strategy, self.sequence)
+            .await?;
+        let fee = self.fee;
         let contract_id = get_contract_id(&asset)?;
         let tx = build_wrap_token_tx(
             &asset,
@@ -171,9 +247,10 @@ impl Cmd {
             &key,
         )?;
 
-        client.send_transaction(&tx).await?;
+        client.send_transaction(&tx).await?.report();
+        rpc::record_sequence(self.sequence_strategy, &public_strkey, sequence + 1)?;
 
-        Ok(hex::encode(&contract_id))
+        Ok(utils::contract_id_to_string(&contract_id.0))
     }
 }
 
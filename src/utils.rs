@@ -1,21 +1,43 @@
+use std::{
+    fmt, fs,
+    io::{self, Cursor, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::ArgEnum;
 use ed25519_dalek::Signer;
 use hex::FromHexError;
 use sha2::{Digest, Sha256};
 use soroban_env_host::xdr::{
-    DecoratedSignature, Signature, SignatureHint, TransactionEnvelope, TransactionV1Envelope,
+    DecoratedSignature, ReadXdr, ScEnvMetaEntry, Signature, SignatureHint, TransactionEnvelope,
+    TransactionV1Envelope,
 };
 use soroban_env_host::{
+    events::DebugEvent,
     im_rc::OrdMap,
+    meta,
     storage::Storage,
     xdr::{
-        ContractDataEntry, Error as XdrError, Hash, LedgerEntry, LedgerEntryData, LedgerEntryExt,
-        LedgerKey, LedgerKeyContractData, ScContractCode, ScObject, ScStatic, ScStatus,
-        ScUnknownErrorCode, ScVal, Transaction, TransactionSignaturePayload,
-        TransactionSignaturePayloadTaggedTransaction, WriteXdr,
+        AccountEntry, AccountEntryExt, AccountId, ContractDataEntry, Error as XdrError, Hash,
+        LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey, LedgerKeyAccount,
+        LedgerKeyContractData, LedgerKeyTrustLine, PublicKey, ScContractCode,
+        ScHostFunctionErrorCode, ScHostStorageErrorCode, ScObject, ScStatic, ScStatus,
+        ScUnknownErrorCode, ScVal, ScVmErrorCode, SequenceNumber, StringM, Thresholds, Transaction,
+        TransactionSignaturePayload, TransactionSignaturePayloadTaggedTransaction, Uint256, VecM,
+        WriteXdr,
     },
     HostError,
 };
-use stellar_strkey::StrkeyPrivateKeyEd25519;
+use stellar_strkey::{StrkeyPrivateKeyEd25519, StrkeyPublicKeyEd25519};
+
+use crate::strval;
 
 pub fn add_contract_to_ledger_entries(
     entries: &mut OrdMap<LedgerKey, LedgerEntry>,
@@ -45,6 +67,467 @@ pub fn add_contract_to_ledger_entries(
     Ok(())
 }
 
+/// Adds a minimal account entry (master key weight 1, no signers or subentries) with the given
+/// balance to the ledger entries, unless the account already exists. Returns whether it was added.
+pub fn ensure_account_entry(
+    entries: &mut OrdMap<LedgerKey, LedgerEntry>,
+    account_id: &AccountId,
+    balance: i64,
+) -> bool {
+    let key = LedgerKey::Account(LedgerKeyAccount {
+        account_id: account_id.clone(),
+    });
+    if entries.contains_key(&key) {
+        return false;
+    }
+    let data = LedgerEntryData::Account(AccountEntry {
+        account_id: account_id.clone(),
+        balance,
+        seq_num: SequenceNumber(0),
+        num_sub_entries: 0,
+        inflation_dest: None,
+        flags: 0,
+        home_domain: StringM::default(),
+        thresholds: Thresholds([1, 0, 0, 0]),
+        signers: VecM::default(),
+        ext: AccountEntryExt::V0,
+    });
+    entries.insert(
+        key,
+        LedgerEntry {
+            last_modified_ledger_seq: 0,
+            data,
+            ext: LedgerEntryExt::V0,
+        },
+    );
+    true
+}
+
+/// Whether adding the contract would replace different code already stored for the contract id
+pub fn would_overwrite_contract(
+    entries: &OrdMap<LedgerKey, LedgerEntry>,
+    contract_id: [u8; 32],
+    contract: &[u8],
+) -> bool {
+    let key = LedgerKey::ContractData(LedgerKeyContractData {
+        contract_id: contract_id.into(),
+        key: ScVal::Static(ScStatic::LedgerKeyContractCode),
+    });
+    match entries.get(&key).map(|entry| &entry.data) {
+        Some(LedgerEntryData::ContractData(ContractDataEntry {
+            val: ScVal::Object(Some(ScObject::ContractCode(ScContractCode::Wasm(existing)))),
+            ..
+        })) => existing.as_slice() != contract,
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/// Whether the contract can be added to the ledger entries: it doesn't replace different code,
+/// `yes` was given, or the user confirmed replacing it
+pub fn confirm_overwrite(
+    entries: &OrdMap<LedgerKey, LedgerEntry>,
+    contract_id: [u8; 32],
+    contract: &[u8],
+    yes: bool,
+) -> bool {
+    yes || !would_overwrite_contract(entries, contract_id, contract)
+        || confirm(&format!(
+            "Overwrite the existing code of contract {}?",
+            hex::encode(contract_id)
+        ))
+}
+
+/// Finds the code of any contract in the ledger entries whose wasm has the given SHA-256 hash
+pub fn find_contract_wasm_by_hash(
+    entries: &OrdMap<LedgerKey, LedgerEntry>,
+    hash: [u8; 32],
+) -> Option<Vec<u8>> {
+    entries.values().find_map(|entry| match &entry.data {
+        LedgerEntryData::ContractData(ContractDataEntry {
+            val: ScVal::Object(Some(ScObject::ContractCode(ScContractCode::Wasm(code)))),
+            ..
+        }) if <[u8; 32]>::from(Sha256::digest(code.as_slice())) == hash => Some(code.to_vec()),
+        _ => None,
+    })
+}
+
+/// Renders a debug event with the contract function it came from, if known, e.g.
+/// "DEBUG [transfer]: insufficient balance"
+pub fn describe_debug_event(event: &DebugEvent, function: Option<&str>) -> String {
+    match function {
+        Some(function) => format!("DEBUG [{}]: {}", function, event),
+        None => format!("DEBUG: {}", event),
+    }
+}
+
+/// Renders a ledger key as its type and salient fields, e.g. for printing footprints
+pub fn describe_ledger_key(key: &LedgerKey) -> String {
+    let account = |AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(k))): &AccountId| {
+        StrkeyPublicKeyEd25519(*k).to_string()
+    };
+    match key {
+        LedgerKey::ContractData(LedgerKeyContractData { contract_id, key }) => {
+            let key = if *key == ScVal::Static(ScStatic::LedgerKeyContractCode) {
+                "<contract code>".to_string()
+            } else {
+                strval::to_string(key).unwrap_or_else(|_| format!("{:?}", key))
+            };
+            format!(
+                "ContractData(contract={}, key={})",
+                hex::encode(contract_id.0),
+                key
+            )
+        }
+        LedgerKey::Account(LedgerKeyAccount { account_id }) => {
+            format!("Account(id={})", account(account_id))
+        }
+        LedgerKey::Trustline(LedgerKeyTrustLine { account_id, asset }) => {
+            format!(
+                "Trustline(account={}, asset={:?})",
+                account(account_id),
+                asset
+            )
+        }
+        _ => format!("{:?}", key),
+    }
+}
+
+/// Returns the env interface version the contract was built against, if it has an env meta section
+pub fn contract_interface_version(wasm: &[u8]) -> Option<u64> {
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        match payload {
+            Ok(wasmparser::Payload::CustomSection(section))
+                if section.name() == "contractenvmetav0" =>
+            {
+                let mut cursor = Cursor::new(section.data());
+                return ScEnvMetaEntry::read_xdr_iter(&mut cursor).find_map(|entry| match entry {
+                    Ok(ScEnvMetaEntry::ScEnvMetaKindInterfaceVersion(v)) => Some(v),
+                    Err(_) => None,
+                });
+            }
+            Ok(_) => (),
+            Err(_) => break,
+        }
+    }
+    None
+}
+
+/// Checks the contract was built against the env interface version of the linked host, returning
+/// the contract's version if it wasn't. Contracts without env meta are left for the host to judge.
+pub fn check_contract_interface_version(wasm: &[u8]) -> Result<(), u64> {
+    match contract_interface_version(wasm) {
+        Some(v) if v != meta::INTERFACE_VERSION => Err(v),
+        _ => Ok(()),
+    }
+}
+
+/// Writes a contract ID to a file, followed by a newline
+pub fn write_contract_id(path: &Path, contract_id: &str) -> Result<(), io::Error> {
+    fs::write(path, format!("{}\n", contract_id))
+}
+
+/// Writes the transactions (base64 xdr) to the file, one per line, e.g. for signing elsewhere
+pub fn write_unsigned_transactions(
+    path: &Path,
+    txs: &[Transaction],
+) -> Result<(), WriteTransactionError> {
+    let mut contents = String::new();
+    for tx in txs {
+        contents.push_str(&tx.to_xdr_base64()?);
+        contents.push('\n');
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+static JSON_PRETTY: AtomicBool = AtomicBool::new(false);
+
+/// Makes [`to_json_string`] pretty-print from now on, instead of the default compact form
+pub fn set_json_pretty() {
+    JSON_PRETTY.store(true, Ordering::SeqCst);
+}
+
+/// Serializes a value as JSON for printing, honoring the global `--json-pretty` flag
+pub fn to_json_string<T: serde::Serialize + ?Sized>(value: &T) -> serde_json::Result<String> {
+    if JSON_PRETTY.load(Ordering::SeqCst) {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+static PROFILE: Mutex<Option<Vec<(String, Duration)>>> = Mutex::new(None);
+
+/// Makes [`record_phase`] keep the durations it's given from now on, for the global `--profile`
+/// flag
+pub fn enable_profile() {
+    *PROFILE.lock().unwrap() = Some(Vec::new());
+}
+
+/// Records that a phase of the command took from `start` until now, if profiling
+pub fn record_phase(phase: &str, start: Instant) {
+    if let Some(phases) = PROFILE.lock().unwrap().as_mut() {
+        phases.push((phase.to_string(), start.elapsed()));
+    }
+}
+
+/// Prints the recorded phases to stderr, if profiling, adding up the ones that happened more than
+/// once (e.g. polling the rpc server)
+pub fn print_profile() {
+    let phases = match PROFILE.lock().unwrap().take() {
+        Some(phases) => phases,
+        None => return,
+    };
+    let mut totals: Vec<(String, u32, Duration)> = Vec::new();
+    for (phase, duration) in phases {
+        match totals.iter_mut().find(|(p, _, _)| *p == phase) {
+            Some((_, count, total)) => {
+                *count += 1;
+                *total += duration;
+            }
+            None => totals.push((phase, 1, duration)),
+        }
+    }
+    let width = totals.iter().map(|(p, _, _)| p.len()).max().unwrap_or(0);
+    eprintln!(
+        "{:width$}  {:>5}  {:>10}",
+        "phase",
+        "count",
+        "ms",
+        width = width
+    );
+    for (phase, count, total) in totals {
+        eprintln!(
+            "{:width$}  {:>5}  {:>10.3}",
+            phase,
+            count,
+            total.as_secs_f64() * 1000.0,
+            width = width
+        );
+    }
+}
+
+/// How contract ids are printed
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ArgEnum)]
+pub enum IdFormat {
+    /// 64 hex characters
+    Hex,
+    /// Strkey (C...)
+    Strkey,
+}
+
+static ID_FORMAT_STRKEY: AtomicBool = AtomicBool::new(false);
+
+/// Sets the format [`contract_id_to_string`] uses from now on
+pub fn set_id_format(format: IdFormat) {
+    ID_FORMAT_STRKEY.store(format == IdFormat::Strkey, Ordering::SeqCst);
+}
+
+/// Renders a contract id for printing, honoring the global `--id-format` flag
+pub fn contract_id_to_string(contract_id: &[u8; 32]) -> String {
+    if ID_FORMAT_STRKEY.load(Ordering::SeqCst) {
+        contract_strkey(contract_id)
+    } else {
+        hex::encode(contract_id)
+    }
+}
+
+const STRKEY_VERSION_CONTRACT: u8 = 2 << 3;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Encodes a contract id as a C... strkey, which stellar-strkey doesn't support yet
+pub fn contract_strkey(contract_id: &[u8; 32]) -> String {
+    let mut data = Vec::with_capacity(35);
+    data.push(STRKEY_VERSION_CONTRACT);
+    data.extend_from_slice(contract_id);
+    data.extend_from_slice(&crc16_xmodem(&data).to_le_bytes());
+
+    let mut encoded = String::new();
+    let mut buffer: u16 = 0;
+    let mut bits: u32 = 0;
+    for byte in data {
+        buffer = (buffer << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(char::from(
+                BASE32_ALPHABET[usize::from((buffer >> bits) & 31)],
+            ));
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        encoded.push(char::from(
+            BASE32_ALPHABET[usize::from((buffer << (5 - bits)) & 31)],
+        ));
+    }
+    encoded
+}
+
+/// Decodes a C... strkey into the contract id, if its version byte and checksum are valid
+pub fn contract_id_from_strkey(strkey: &str) -> Option<[u8; 32]> {
+    // 35 bytes (version, id and checksum) take 56 characters
+    if strkey.len() != 56 {
+        return None;
+    }
+    let mut data = Vec::with_capacity(35);
+    let mut buffer: u16 = 0;
+    let mut bits: u32 = 0;
+    for c in strkey.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|a| *a == c)?;
+        buffer = (buffer << 5) | u16::try_from(value).unwrap();
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            data.push(u8::try_from(buffer >> bits).unwrap());
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    // The bits left over pad the last character
+    if buffer != 0 {
+        return None;
+    }
+    let (payload, checksum) = data.split_at(33);
+    if payload[0] != STRKEY_VERSION_CONTRACT || checksum != crc16_xmodem(payload).to_le_bytes() {
+        return None;
+    }
+    payload[1..].try_into().ok()
+}
+
+fn crc16_xmodem(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in data {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            };
+        }
+    }
+    crc
+}
+
+/// Shared by [`run_with_timeout`] and its worker, which must call [`Deadline::begin_commit`]
+/// before making a change that outlives it (such as writing the ledger file).
+#[derive(Clone, Debug, Default)]
+pub struct Deadline {
+    state: Arc<AtomicU8>,
+    budget: Arc<Mutex<Option<(u64, u64)>>>,
+}
+
+const DEADLINE_RUNNING: u8 = 0;
+const DEADLINE_COMMITTING: u8 = 1;
+const DEADLINE_PASSED: u8 = 2;
+
+impl Deadline {
+    /// Returns false once the timeout has passed, in which case the caller has already reported
+    /// the timeout and the worker must not commit anything. Once this returned true, the caller
+    /// waits for the worker to finish instead of timing out.
+    pub fn begin_commit(&self) -> bool {
+        self.state
+            .compare_exchange(
+                DEADLINE_RUNNING,
+                DEADLINE_COMMITTING,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+    }
+
+    /// Records the cpu instructions and memory bytes the worker consumed so far
+    pub fn record_budget(&self, cpu_insns: u64, mem_bytes: u64) {
+        *self.budget.lock().unwrap() = Some((cpu_insns, mem_bytes));
+    }
+
+    /// Cpu instructions and memory bytes last recorded by the worker, if any
+    pub fn budget(&self) -> Option<(u64, u64)> {
+        *self.budget.lock().unwrap()
+    }
+
+    fn pass(&self) -> bool {
+        self.state
+            .compare_exchange(
+                DEADLINE_RUNNING,
+                DEADLINE_PASSED,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+    }
+}
+
+/// Runs `f` in a worker thread and waits at most `timeout` for its result. The host isn't
+/// interruptible, so a worker that runs over is abandoned (and torn down when the process exits),
+/// unless it already began committing through `deadline`, which is then waited for.
+pub fn run_with_timeout<T, F>(
+    timeout: Duration,
+    deadline: &Deadline,
+    f: F,
+) -> Result<T, RecvTimeoutError>
+where
+    T: Send + 'static,
+    F: FnOnce(&Deadline) -> T + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let worker_deadline = deadline.clone();
+    thread::spawn(move || {
+        let _ = sender.send(f(&worker_deadline));
+    });
+    match receiver.recv_timeout(timeout) {
+        Err(RecvTimeoutError::Timeout) if !deadline.pass() => {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        }
+        res => res,
+    }
+}
+
+/// Asks the user for confirmation on stdin. Anything but "y" or "yes" (or a failed read) is a no.
+pub fn confirm(prompt: &str) -> bool {
+    eprint!("{} [y/N] ", prompt);
+    let _ = io::stderr().flush();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Quotes the value for a POSIX shell, unless it only contains characters that are safe unquoted
+pub fn shell_quote(value: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c);
+    if !value.is_empty() && value.chars().all(is_safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Reads a wasm file, replacing the most common io errors with friendlier messages
+pub fn read_wasm_file(path: &Path) -> Result<Vec<u8>, io::Error> {
+    match fs::metadata(path) {
+        Ok(m) if m.is_dir() => {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "is a directory, expected a .wasm file",
+            ))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "file not found"))
+        }
+        _ => {}
+    }
+    fs::read(path).map_err(|e| match e.kind() {
+        io::ErrorKind::PermissionDenied => io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "permission denied, check the file's read permissions",
+        ),
+        _ => e,
+    })
+}
+
 pub fn padded_hex_from_str(s: &String, n: usize) -> Result<Vec<u8>, FromHexError> {
     let mut decoded = vec![0u8; n];
     let padded = format!("{:0>width$}", s, width = n * 2);
@@ -52,9 +535,14 @@ pub fn padded_hex_from_str(s: &String, n: usize) -> Result<Vec<u8>, FromHexError
     Ok(decoded)
 }
 
+/// Network id (SHA-256 of the passphrase) included in the payloads that get signed
+pub fn network_id(network_passphrase: &str) -> [u8; 32] {
+    Sha256::digest(network_passphrase).into()
+}
+
 pub fn transaction_hash(tx: &Transaction, network_passphrase: &str) -> Result<[u8; 32], XdrError> {
     let signature_payload = TransactionSignaturePayload {
-        network_id: Hash(Sha256::digest(network_passphrase).into()),
+        network_id: Hash(network_id(network_passphrase)),
         tagged_transaction: TransactionSignaturePayloadTaggedTransaction::Tx(tx.clone()),
     };
     Ok(Sha256::digest(signature_payload.to_xdr()?).into())
@@ -65,24 +553,83 @@ pub fn sign_transaction(
     tx: &Transaction,
     network_passphrase: &str,
 ) -> Result<TransactionEnvelope, XdrError> {
+    Ok(sign_transaction_with_hash(key, tx, network_passphrase)?.0)
+}
+
+/// Signs the transaction, also returning the hash that was signed (i.e. the transaction id)
+pub fn sign_transaction_with_hash(
+    key: &ed25519_dalek::Keypair,
+    tx: &Transaction,
+    network_passphrase: &str,
+) -> Result<(TransactionEnvelope, [u8; 32]), XdrError> {
     let tx_hash = transaction_hash(tx, network_passphrase)?;
-    let tx_signature = key.sign(&tx_hash);
+    let envelope = TransactionEnvelope::Tx(TransactionV1Envelope {
+        tx: tx.clone(),
+        signatures: vec![decorated_signature(key, &tx_hash)?].try_into()?,
+    });
+    Ok((envelope, tx_hash))
+}
+
+/// Adds the signature of another key to a signed (v1) transaction envelope, e.g. for accounts
+/// with multiple signers
+pub fn add_signature(
+    envelope: TransactionEnvelope,
+    key: &ed25519_dalek::Keypair,
+    network_passphrase: &str,
+) -> Result<TransactionEnvelope, XdrError> {
+    match envelope {
+        TransactionEnvelope::Tx(TransactionV1Envelope { tx, signatures }) => {
+            let tx_hash = transaction_hash(&tx, network_passphrase)?;
+            let mut signatures = signatures.to_vec();
+            signatures.push(decorated_signature(key, &tx_hash)?);
+            Ok(TransactionEnvelope::Tx(TransactionV1Envelope {
+                tx,
+                signatures: signatures.try_into()?,
+            }))
+        }
+        _ => Err(XdrError::Invalid),
+    }
+}
 
-    let decorated_signature = DecoratedSignature {
+fn decorated_signature(
+    key: &ed25519_dalek::Keypair,
+    tx_hash: &[u8; 32],
+) -> Result<DecoratedSignature, XdrError> {
+    let tx_signature = key.sign(tx_hash);
+    Ok(DecoratedSignature {
         hint: SignatureHint(key.public.to_bytes()[28..].try_into()?),
         signature: Signature(tx_signature.to_bytes().try_into()?),
-    };
+    })
+}
 
-    Ok(TransactionEnvelope::Tx(TransactionV1Envelope {
-        tx: tx.clone(),
-        signatures: vec![decorated_signature].try_into()?,
-    }))
+#[derive(thiserror::Error, Debug)]
+pub enum ParseContractIdError {
+    #[error(transparent)]
+    Hex(#[from] FromHexError),
+    #[error("invalid contract strkey (C...)")]
+    InvalidStrkey,
 }
 
-pub fn contract_id_from_str(contract_id: &String) -> Result<[u8; 32], FromHexError> {
-    padded_hex_from_str(contract_id, 32)?
+/// Parses a contract id given in hex, or as a C... strkey like the ones `--id-format strkey`
+/// prints
+pub fn contract_id_from_str(contract_id: &String) -> Result<[u8; 32], ParseContractIdError> {
+    // Hex ids can start with a C too, but are unlikely to make a valid strkey checksum
+    if contract_id.starts_with('C') && contract_id.len() == 56 {
+        if let Some(contract_id) = contract_id_from_strkey(contract_id) {
+            return Ok(contract_id);
+        }
+        if !contract_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseContractIdError::InvalidStrkey);
+        }
+    }
+    // Accept ids pasted with a 0x prefix too
+    let contract_id = contract_id
+        .strip_prefix("0x")
+        .or_else(|| contract_id.strip_prefix("0X"))
+        .unwrap_or(contract_id);
+    padded_hex_from_str(&contract_id.to_string(), 32)?
         .try_into()
-        .map_err(|_| FromHexError::InvalidStringLength)
+        .map_err(|_| FromHexError::InvalidStringLength.into())
 }
 
 pub fn get_contract_wasm_from_storage(
@@ -109,22 +656,109 @@ pub fn vec_to_hash(res: &ScVal) -> Result<String, XdrError> {
         for (i, b) in res_hash.iter().enumerate() {
             hash_bytes[i] = *b;
         }
-        Ok(hex::encode(hash_bytes))
+        Ok(contract_id_to_string(&hash_bytes))
     } else {
         Err(XdrError::Invalid)
     }
 }
 
+/// Names the kind and code of a status, e.g. "host storage error (UnknownError)"
+pub fn describe_status(status: &ScStatus) -> String {
+    match status {
+        ScStatus::Ok => "ok".to_string(),
+        ScStatus::UnknownError(code) => format!("unknown error ({:?})", code),
+        ScStatus::HostValueError(code) => format!("host value error ({:?})", code),
+        ScStatus::HostObjectError(code) => format!("host object error ({:?})", code),
+        ScStatus::HostFunctionError(code) => format!("host function error ({:?})", code),
+        ScStatus::HostStorageError(code) => format!("host storage error ({:?})", code),
+        ScStatus::HostContextError(code) => format!("host context error ({:?})", code),
+        ScStatus::VmError(code) => format!("vm error ({:?})", code),
+        ScStatus::ContractError(code) => format!("contract error #{}", code),
+    }
+}
+
+/// Decodes the status carried by a host error, if it is a valid `ScStatus`
+pub fn host_error_status(error: &HostError) -> Option<ScStatus> {
+    error.status.try_into().ok()
+}
+
+/// Explains the statuses contracts commonly fail with, and describes the others
+pub fn readable_status(status: &ScStatus) -> String {
+    match status {
+        ScStatus::ContractError(code) => format!("contract panicked with error code {}", code),
+        ScStatus::VmError(ScVmErrorCode::TrapUnreachable) => {
+            "contract panicked (hit an unreachable instruction)".to_string()
+        }
+        ScStatus::HostStorageError(ScHostStorageErrorCode::AccessToUnknownEntry) => {
+            "accessed a ledger entry outside of the footprint".to_string()
+        }
+        ScStatus::HostStorageError(ScHostStorageErrorCode::MissingKeyInGet) => {
+            "read a ledger entry that doesn't exist".to_string()
+        }
+        ScStatus::HostFunctionError(ScHostFunctionErrorCode::InputArgsWrongLength) => {
+            "wrong number of arguments for the function".to_string()
+        }
+        ScStatus::HostFunctionError(ScHostFunctionErrorCode::InputArgsWrongType) => {
+            "wrong type of argument for the function".to_string()
+        }
+        _ => describe_status(status),
+    }
+}
+
+/// Describes a host error by its decoded status, falling back to its debug representation
+pub fn describe_host_error(error: &HostError) -> String {
+    host_error_status(error).map_or_else(|| format!("{:?}", error), |s| readable_status(&s))
+}
+
+static VERBOSE_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Makes [`HostErrorDisplay`] append the raw debug representation of host errors from now on
+pub fn set_verbose_errors() {
+    VERBOSE_ERRORS.store(true, Ordering::SeqCst);
+}
+
+/// Displays a host error readably (see [`describe_host_error`]), instead of the debug
+/// representation its own `Display` prints
+pub struct HostErrorDisplay<'a>(pub &'a HostError);
+
+impl fmt::Display for HostErrorDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", describe_host_error(self.0))?;
+        if VERBOSE_ERRORS.load(Ordering::SeqCst) {
+            write!(f, "\n{:?}", self.0)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ParsePrivateKeyError {
     #[error("cannot parse private key")]
     CannotParsePrivateKey,
+    #[error("cannot parse hd path {0} (expected e.g. m/44'/148'/0', with hardened indexes only)")]
+    CannotParseHdPath(String),
+    #[error("either a secret key or a mnemonic is required")]
+    MissingSigningKey,
+    #[error("mnemonic has {0} words (expected 12, 15, 18, 21 or 24)")]
+    InvalidMnemonicLength(usize),
+    #[error("mnemonic word {0} is not in the BIP-39 english wordlist")]
+    UnknownMnemonicWord(String),
+    #[error("mnemonic checksum does not match, one of its words is probably mistyped")]
+    InvalidMnemonicChecksum,
+    #[error("cannot parse mnemonic")]
+    CannotParseMnemonic,
+    #[error("mnemonics are not supported, rebuild with the mnemonic feature enabled")]
+    MnemonicNotSupported,
 }
 
 pub fn parse_private_key(strkey: &str) -> Result<ed25519_dalek::Keypair, ParsePrivateKeyError> {
     let seed = StrkeyPrivateKeyEd25519::from_string(strkey)
         .map_err(|_| ParsePrivateKeyError::CannotParsePrivateKey)?;
-    let secret_key = ed25519_dalek::SecretKey::from_bytes(&seed.0)
+    keypair_from_seed(&seed.0)
+}
+
+fn keypair_from_seed(seed: &[u8]) -> Result<ed25519_dalek::Keypair, ParsePrivateKeyError> {
+    let secret_key = ed25519_dalek::SecretKey::from_bytes(seed)
         .map_err(|_| ParsePrivateKeyError::CannotParsePrivateKey)?;
     let public_key = (&secret_key).into();
     Ok(ed25519_dalek::Keypair {
@@ -133,6 +767,130 @@ pub fn parse_private_key(strkey: &str) -> Result<ed25519_dalek::Keypair, ParsePr
     })
 }
 
+/// SEP-0005 derivation path of the first account of a mnemonic
+pub const DEFAULT_HD_PATH: &str = "m/44'/148'/0'";
+
+/// Derives the keypair at the given SEP-0005 path from a BIP-39 mnemonic (without passphrase)
+#[cfg(feature = "mnemonic")]
+pub fn parse_mnemonic(
+    mnemonic: &str,
+    hd_path: &str,
+) -> Result<ed25519_dalek::Keypair, ParsePrivateKeyError> {
+    let indexes = parse_hd_path(hd_path)?;
+    let words = mnemonic.split_whitespace().collect::<Vec<_>>();
+    let seed = bip39::Mnemonic::parse_normalized(&words.join(" "))
+        .map_err(|e| match e {
+            bip39::Error::BadWordCount(count) => ParsePrivateKeyError::InvalidMnemonicLength(count),
+            bip39::Error::UnknownWord(i) => {
+                ParsePrivateKeyError::UnknownMnemonicWord(words[i].to_string())
+            }
+            bip39::Error::InvalidChecksum => ParsePrivateKeyError::InvalidMnemonicChecksum,
+            _ => ParsePrivateKeyError::CannotParseMnemonic,
+        })?
+        .to_seed_normalized("");
+
+    // SLIP-0010 ed25519 derivation, which only has hardened children
+    let mut node = hmac_sha512(b"ed25519 seed", &seed);
+    for index in indexes {
+        let mut data = vec![0u8];
+        data.extend_from_slice(&node[..32]);
+        data.extend_from_slice(&(index | 0x8000_0000).to_be_bytes());
+        node = hmac_sha512(&node[32..], &data);
+    }
+    keypair_from_seed(&node[..32])
+}
+
+#[cfg(not(feature = "mnemonic"))]
+pub fn parse_mnemonic(
+    _mnemonic: &str,
+    hd_path: &str,
+) -> Result<ed25519_dalek::Keypair, ParsePrivateKeyError> {
+    parse_hd_path(hd_path)?;
+    Err(ParsePrivateKeyError::MnemonicNotSupported)
+}
+
+fn parse_hd_path(hd_path: &str) -> Result<Vec<u32>, ParsePrivateKeyError> {
+    hd_path
+        .strip_prefix("m/")
+        .ok_or_else(|| ParsePrivateKeyError::CannotParseHdPath(hd_path.to_string()))?
+        .split('/')
+        .map(|index| {
+            index
+                .strip_suffix('\'')
+                .and_then(|index| index.parse::<u32>().ok())
+                .filter(|index| *index < 0x8000_0000)
+                .ok_or_else(|| ParsePrivateKeyError::CannotParseHdPath(hd_path.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(feature = "mnemonic")]
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    use hmac::{Hmac, Mac};
+    let mut mac =
+        Hmac::<sha2::Sha512>::new_from_slice(key).expect("hmac accepts keys of any length");
+    mac.update(data);
+    let mut output = [0u8; 64];
+    output.copy_from_slice(&mac.finalize().into_bytes());
+    output
+}
+
+/// Gets the signing keypair from either a secret key or a mnemonic
+pub fn signing_key(
+    secret_key: Option<&str>,
+    mnemonic: Option<&str>,
+    hd_path: &str,
+) -> Result<ed25519_dalek::Keypair, ParsePrivateKeyError> {
+    match (secret_key, mnemonic) {
+        (Some(secret_key), _) => parse_private_key(secret_key),
+        (None, Some(mnemonic)) => parse_mnemonic(mnemonic, hd_path),
+        (None, None) => Err(ParsePrivateKeyError::MissingSigningKey),
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum WriteTransactionError {
+    #[error(transparent)]
+    Xdr(#[from] XdrError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ParseAmountError {
+    #[error("cannot parse amount {0}")]
+    CannotParseAmount(String),
+    #[error("amount {amount} has more than {decimals} fractional digits")]
+    TooManyFractionalDigits { amount: String, decimals: u32 },
+    #[error("amount {0} is out of range")]
+    AmountOutOfRange(String),
+}
+
+/// Converts an amount in display units (e.g. `1.5`) to the raw integer amount of a token with
+/// the given number of decimals
+pub fn parse_amount(amount: &str, decimals: u32) -> Result<i128, ParseAmountError> {
+    let (negative, digits) = match amount.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, amount),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+        return Err(ParseAmountError::CannotParseAmount(amount.to_string()));
+    }
+    if fraction.len() > decimals as usize {
+        return Err(ParseAmountError::TooManyFractionalDigits {
+            amount: amount.to_string(),
+            decimals,
+        });
+    }
+    let padded = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    let raw = padded
+        .parse::<i128>()
+        .map_err(|_| ParseAmountError::AmountOutOfRange(amount.to_string()))?;
+    Ok(if negative { -raw } else { raw })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,4 +914,232 @@ mod tests {
         ];
         assert_eq!(expected_private_key, keypair.secret.to_bytes());
     }
+
+    #[test]
+    fn test_contract_id_from_str() {
+        let expected: [u8; 32] = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0x01, 0xab,
+        ];
+        let padded = format!("0x{:0>64}", "1ab");
+        for id in ["1ab", "0x1ab", "0X1AB", padded.as_str()] {
+            assert_eq!(contract_id_from_str(&id.to_string()).unwrap(), expected);
+        }
+        assert!(contract_id_from_str(&"0xzz".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("--arg"), "--arg");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("{\"a\": 1}"), "'{\"a\": 1}'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_describe_status() {
+        assert_eq!(
+            describe_status(&ScStatus::HostStorageError(
+                soroban_env_host::xdr::ScHostStorageErrorCode::UnknownError
+            )),
+            "host storage error (UnknownError)"
+        );
+        assert_eq!(
+            describe_status(&ScStatus::ContractError(3)),
+            "contract error #3"
+        );
+    }
+
+    #[test]
+    fn test_read_wasm_file() {
+        let dir = std::env::temp_dir();
+        let err = read_wasm_file(&dir).unwrap_err();
+        assert_eq!(err.to_string(), "is a directory, expected a .wasm file");
+
+        let err = read_wasm_file(&dir.join("soroban-cli-missing.wasm")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "file not found");
+    }
+
+    #[test]
+    fn test_network_id() {
+        assert_eq!(
+            hex::encode(network_id("Test SDF Network ; September 2015")),
+            "cee0302d59844d32bdca915c8203dd44b33fbb7edc19051ea37abedf28ecd472"
+        );
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("1.5", 7), Ok(15_000_000));
+        assert_eq!(parse_amount("42", 2), Ok(4_200));
+        assert_eq!(parse_amount("-0.01", 2), Ok(-1));
+        assert_eq!(parse_amount(".5", 1), Ok(5));
+        assert_eq!(
+            parse_amount("1.234", 2),
+            Err(ParseAmountError::TooManyFractionalDigits {
+                amount: "1.234".to_string(),
+                decimals: 2
+            })
+        );
+        assert!(parse_amount("1.2.3", 7).is_err());
+        assert!(parse_amount("", 7).is_err());
+        assert!(parse_amount("abc", 7).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mnemonic")]
+    fn test_parse_mnemonic() {
+        // First test vector of SEP-0005
+        let mnemonic = "illness spike retreat truth genius clock brain pass fit cave bargain toe";
+        let keypair = parse_mnemonic(mnemonic, DEFAULT_HD_PATH).unwrap();
+        let expected =
+            parse_private_key("SBGWSG6BTNCKCOB3DIFBGCVMUPQFYPA2G4O34RMTB343OYPXU5DJDVMN").unwrap();
+        assert_eq!(keypair.to_bytes(), expected.to_bytes());
+
+        assert!(parse_mnemonic(mnemonic, "m/44/148/0").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mnemonic")]
+    fn test_parse_mnemonic_invalid() {
+        assert!(matches!(
+            parse_mnemonic(
+                "illness spike retreat truth genius clock brain pass fit cave bargain tooe",
+                DEFAULT_HD_PATH
+            ),
+            Err(ParsePrivateKeyError::UnknownMnemonicWord(word)) if word == "tooe"
+        ));
+        // The valid mnemonic of zero entropy ends with "about"
+        assert!(matches!(
+            parse_mnemonic(&["abandon"; 12].join(" "), DEFAULT_HD_PATH),
+            Err(ParsePrivateKeyError::InvalidMnemonicChecksum)
+        ));
+        assert!(matches!(
+            parse_mnemonic("illness spike retreat", DEFAULT_HD_PATH),
+            Err(ParsePrivateKeyError::InvalidMnemonicLength(3))
+        ));
+    }
+
+    #[test]
+    fn test_add_signature() {
+        use soroban_env_host::xdr::{Memo, MuxedAccount, Preconditions, TransactionExt};
+
+        let key =
+            parse_private_key("SBFGFF27Y64ZUGFAIG5AMJGQODZZKV2YQKAVUUN4HNE24XZXD2OEUVUP").unwrap();
+        let other =
+            parse_private_key("SBGWSG6BTNCKCOB3DIFBGCVMUPQFYPA2G4O34RMTB343OYPXU5DJDVMN").unwrap();
+        let tx = Transaction {
+            source_account: MuxedAccount::Ed25519(Uint256(key.public.to_bytes())),
+            fee: 100,
+            seq_num: SequenceNumber(1),
+            cond: Preconditions::None,
+            memo: Memo::None,
+            operations: VecM::default(),
+            ext: TransactionExt::V0,
+        };
+        let passphrase = "Test SDF Network ; September 2015";
+        let envelope = sign_transaction(&key, &tx, passphrase).unwrap();
+        let envelope = add_signature(envelope, &other, passphrase).unwrap();
+
+        let signatures = match envelope {
+            TransactionEnvelope::Tx(TransactionV1Envelope { signatures, .. }) => signatures,
+            _ => panic!("expected a v1 envelope"),
+        };
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[1].hint.0, other.public.to_bytes()[28..]);
+    }
+
+    #[test]
+    fn test_contract_strkey() {
+        assert_eq!(
+            contract_strkey(&[0; 32]),
+            "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABSC4"
+        );
+        let mut contract_id = [0; 32];
+        for (i, b) in contract_id.iter_mut().enumerate() {
+            *b = u8::try_from(i).unwrap();
+        }
+        assert_eq!(
+            contract_strkey(&contract_id),
+            "CAAACAQDAQCQMBYIBEFAWDANBYHRAEISCMKBKFQXDAMRUGY4DUPB6N4O"
+        );
+    }
+
+    #[test]
+    fn test_contract_id_from_strkey() {
+        let mut contract_id = [0; 32];
+        for (i, b) in contract_id.iter_mut().enumerate() {
+            *b = u8::try_from(i).unwrap();
+        }
+        let strkey = contract_strkey(&contract_id);
+        assert_eq!(contract_id_from_strkey(&strkey), Some(contract_id));
+        assert_eq!(
+            contract_id_from_str(&strkey).unwrap(),
+            contract_id,
+            "ids printed with --id-format strkey are accepted back"
+        );
+
+        // Wrong checksum, version byte (a G... key) and length
+        let mut corrupted = strkey.clone().into_bytes();
+        corrupted[10] = if corrupted[10] == b'A' { b'B' } else { b'A' };
+        let corrupted = String::from_utf8(corrupted).unwrap();
+        assert_eq!(contract_id_from_strkey(&corrupted), None);
+        assert!(matches!(
+            contract_id_from_str(&corrupted),
+            Err(ParseContractIdError::InvalidStrkey)
+        ));
+        let account = stellar_strkey::StrkeyPublicKeyEd25519(contract_id).to_string();
+        assert_eq!(contract_id_from_strkey(&account), None);
+        assert_eq!(contract_id_from_strkey(&strkey[..55]), None);
+    }
+
+    #[test]
+    fn test_ensure_account_entry() {
+        let mut entries = OrdMap::new();
+        let account_id = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([1; 32])));
+        assert!(ensure_account_entry(&mut entries, &account_id, 100));
+        assert!(!ensure_account_entry(&mut entries, &account_id, 200));
+        assert_eq!(entries.len(), 1);
+        match &entries.values().next().unwrap().data {
+            LedgerEntryData::Account(account) => assert_eq!(account.balance, 100),
+            _ => panic!("expected an account entry"),
+        }
+    }
+
+    #[test]
+    fn test_readable_status() {
+        assert_eq!(
+            readable_status(&ScStatus::ContractError(3)),
+            "contract panicked with error code 3"
+        );
+        assert_eq!(
+            readable_status(&ScStatus::HostStorageError(
+                ScHostStorageErrorCode::UnknownError
+            )),
+            "host storage error (UnknownError)"
+        );
+    }
+
+    #[test]
+    fn test_run_with_timeout() {
+        let deadline = Deadline::default();
+        let (sender, receiver) = mpsc::channel();
+        let res = run_with_timeout(Duration::from_millis(10), &deadline, move |d| {
+            thread::sleep(Duration::from_millis(100));
+            sender.send(d.begin_commit()).unwrap();
+        });
+        assert_eq!(res, Err(RecvTimeoutError::Timeout));
+        // The abandoned worker isn't allowed to commit
+        assert!(!receiver.recv().unwrap());
+
+        // A worker that began committing is waited for
+        let deadline = Deadline::default();
+        let res = run_with_timeout(Duration::from_millis(50), &deadline, |d| {
+            assert!(d.begin_commit());
+            thread::sleep(Duration::from_millis(200));
+            42
+        });
+        assert_eq!(res, Ok(42));
+    }
 }
@@ -0,0 +1,161 @@
+use std::{
+    fmt::Debug,
+    io::{self, Read},
+};
+
+use clap::Parser;
+use soroban_env_host::xdr::{
+    DecoratedSignature, Error as XdrError, MuxedAccount, ReadXdr, TransactionEnvelope,
+    TransactionV1Envelope,
+};
+use stellar_strkey::StrkeyPublicKeyEd25519;
+
+use crate::utils;
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Signed transaction envelope (base64 xdr), `-` reads it from stdin
+    #[clap(long = "envelope-xdr")]
+    envelope_xdr: String,
+    /// Network passphrase the transaction was signed for
+    #[clap(long = "network-passphrase")]
+    network_passphrase: String,
+    /// Public key (G...) of another account expected to have signed, besides the source accounts
+    /// of the transaction and its operations (can be repeated)
+    #[clap(long = "signer")]
+    signers: Vec<StrkeyPublicKeyEd25519>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("reading transaction envelope from stdin: {0}")]
+    CannotReadEnvelopeFromStdin(io::Error),
+    #[error("parsing transaction envelope: {0}")]
+    CannotParseEnvelope(XdrError),
+    #[error("only v1 transaction envelopes can be verified")]
+    UnsupportedEnvelope,
+    #[error("xdr processing error: {0}")]
+    Xdr(#[from] XdrError),
+    #[error("{invalid} of {total} signatures are not valid")]
+    InvalidSignatures { invalid: usize, total: usize },
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        let input = if self.envelope_xdr == "-" {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(Error::CannotReadEnvelopeFromStdin)?;
+            input
+        } else {
+            self.envelope_xdr.clone()
+        };
+        let envelope = TransactionEnvelope::from_xdr_base64(input.trim().to_string())
+            .map_err(Error::CannotParseEnvelope)?;
+        let (tx, signatures) = match &envelope {
+            TransactionEnvelope::Tx(TransactionV1Envelope { tx, signatures }) => (tx, signatures),
+            _ => return Err(Error::UnsupportedEnvelope),
+        };
+
+        let mut candidates = vec![account_key(&tx.source_account)];
+        for op in tx.operations.iter() {
+            if let Some(source_account) = &op.source_account {
+                candidates.push(account_key(source_account));
+            }
+        }
+        candidates.extend(self.signers.iter().map(|s| s.0));
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let tx_hash = utils::transaction_hash(tx, &self.network_passphrase)?;
+        eprintln!("Transaction hash: {}", hex::encode(tx_hash));
+        let mut invalid = 0;
+        for (i, signature) in signatures.iter().enumerate() {
+            match verify_signature(&candidates, &tx_hash, signature) {
+                Some(signer) => {
+                    let signer = StrkeyPublicKeyEd25519(signer).to_string();
+                    println!("#{}: valid, signed by {}", i, signer);
+                }
+                None => {
+                    invalid += 1;
+                    println!(
+                        "#{}: invalid, no known signer with hint {} signed this transaction",
+                        i,
+                        hex::encode(signature.hint.0)
+                    );
+                }
+            }
+        }
+        if invalid > 0 {
+            return Err(Error::InvalidSignatures {
+                invalid,
+                total: signatures.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn account_key(account: &MuxedAccount) -> [u8; 32] {
+    match account {
+        MuxedAccount::Ed25519(key) => key.0,
+        MuxedAccount::MuxedEd25519(account) => account.ed25519.0,
+    }
+}
+
+/// Returns the candidate whose key made the signature of the transaction hash, if any does
+fn verify_signature(
+    candidates: &[[u8; 32]],
+    tx_hash: &[u8; 32],
+    signature: &DecoratedSignature,
+) -> Option<[u8; 32]> {
+    let sig = ed25519_dalek::Signature::try_from(signature.signature.0.as_slice()).ok()?;
+    candidates.iter().copied().find(|candidate| {
+        candidate[28..] == signature.hint.0
+            && ed25519_dalek::PublicKey::from_bytes(candidate)
+                .map_or(false, |key| key.verify_strict(tx_hash, &sig).is_ok())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_env_host::xdr::{
+        Memo, Preconditions, SequenceNumber, Transaction, TransactionExt, Uint256, VecM,
+    };
+
+    #[test]
+    fn test_verify_signature() {
+        let key =
+            utils::parse_private_key("SBFGFF27Y64ZUGFAIG5AMJGQODZZKV2YQKAVUUN4HNE24XZXD2OEUVUP")
+                .unwrap();
+        let public = key.public.to_bytes();
+        let tx = Transaction {
+            source_account: MuxedAccount::Ed25519(Uint256(public)),
+            fee: 100,
+            seq_num: SequenceNumber(1),
+            cond: Preconditions::None,
+            memo: Memo::None,
+            operations: VecM::default(),
+            ext: TransactionExt::V0,
+        };
+        let passphrase = "Test SDF Network ; September 2015";
+        let (envelope, tx_hash) = utils::sign_transaction_with_hash(&key, &tx, passphrase).unwrap();
+        let signature = match envelope {
+            TransactionEnvelope::Tx(e) => e.signatures[0].clone(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            verify_signature(&[[1; 32], public], &tx_hash, &signature),
+            Some(public)
+        );
+        assert_eq!(verify_signature(&[[1; 32]], &tx_hash, &signature), None);
+        let other_network = utils::transaction_hash(&tx, "Public").unwrap();
+        assert_eq!(
+            verify_signature(&[public], &other_network, &signature),
+            None
+        );
+    }
+}
@@ -1,6 +1,11 @@
 use clap::{ArgEnum, Parser};
 use soroban_env_host::xdr::{self};
 
+use crate::{
+    strval::{self, StrValError},
+    utils,
+};
+
 #[derive(Parser, Debug)]
 pub struct Cmd {
     /// XDR type to decode to
@@ -20,6 +25,8 @@ pub enum Output {
     Default,
     /// Json representation
     Json,
+    /// Human readable string form, the same as invoke prints results in (ScVal only)
+    String,
 }
 
 impl Default for Output {
@@ -34,6 +41,10 @@ pub enum Error {
     Xdr(#[from] xdr::Error),
     #[error("generating json: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("string output is only supported for ScVal, not {0:?}")]
+    StringOutputRequiresScVal(xdr::TypeVariant),
+    #[error("generating string: {0}")]
+    StrVal(#[from] StrValError),
 }
 
 impl Cmd {
@@ -43,10 +54,11 @@ impl Cmd {
 
         match self.output {
             Output::Default => println!("{:#?}", value),
-            Output::Json => println!(
-                "{}",
-                serde_json::to_string_pretty(&value).map_err(Error::Json)?
-            ),
+            Output::Json => println!("{}", utils::to_json_string(&value).map_err(Error::Json)?),
+            Output::String => match value {
+                xdr::Type::ScVal(v) => println!("{}", strval::to_string(&v)?),
+                _ => return Err(Error::StringOutputRequiresScVal(self.r#type)),
+            },
         }
 
         Ok(())
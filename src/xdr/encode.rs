@@ -0,0 +1,65 @@
+use clap::{ArgEnum, Parser};
+use soroban_env_host::xdr::{self, ScSpecTypeDef, WriteXdr};
+
+use crate::strval::{self, StrValError};
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Type of the value to encode as an ScVal
+    #[clap(long, arg_enum)]
+    r#type: ValueType,
+    /// Value to encode, written like an invoke argument
+    #[clap(long)]
+    value: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ArgEnum)]
+pub enum ValueType {
+    Bool,
+    U32,
+    I32,
+    U64,
+    I64,
+    BigInt,
+    Symbol,
+    Bytes,
+    AccountId,
+}
+
+impl ValueType {
+    fn spec_type(self) -> ScSpecTypeDef {
+        match self {
+            ValueType::Bool => ScSpecTypeDef::Bool,
+            ValueType::U32 => ScSpecTypeDef::U32,
+            ValueType::I32 => ScSpecTypeDef::I32,
+            ValueType::U64 => ScSpecTypeDef::U64,
+            ValueType::I64 => ScSpecTypeDef::I64,
+            ValueType::BigInt => ScSpecTypeDef::BigInt,
+            ValueType::Symbol => ScSpecTypeDef::Symbol,
+            ValueType::Bytes => ScSpecTypeDef::Bytes,
+            ValueType::AccountId => ScSpecTypeDef::AccountId,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("parsing value {value}: {error}")]
+    CannotParseValue { value: String, error: StrValError },
+    #[error("generating xdr: {0}")]
+    Xdr(#[from] xdr::Error),
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        let value =
+            strval::from_string(&self.value, &self.r#type.spec_type(), &[]).map_err(|e| {
+                Error::CannotParseValue {
+                    value: self.value.clone(),
+                    error: e,
+                }
+            })?;
+        println!("{}", value.to_xdr_base64()?);
+        Ok(())
+    }
+}